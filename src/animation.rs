@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+
+use crate::components::Velocity;
+
+/// Size of a single frame in the walk cycle sprite sheets, in pixels.
+const FRAME_SIZE: u32 = 32;
+
+/// Frames in a walk cycle sheet, laid out in a single row with the idle
+/// frame first.
+const WALK_FRAMES: u32 = 4;
+
+/// Seconds each frame of a walk cycle stays on screen.
+const FRAME_TIME: f32 = 0.1;
+
+/// Speed below which an entity counts as standing still.
+const MOVING_SPEED: f32 = 1.;
+
+/// Range of atlas frames to cycle through while moving, and the one to rest
+/// on while standing still.
+#[derive(Component)]
+pub struct AnimationIndices {
+    pub first: usize,
+    pub last: usize,
+    pub idle: usize,
+}
+
+impl AnimationIndices {
+    /// Indices for a sheet laid out like `walk_cycle_layout`.
+    pub fn walk_cycle() -> Self {
+        Self {
+            first: 0,
+            last: WALK_FRAMES as usize - 1,
+            idle: 0,
+        }
+    }
+}
+
+/// Time until the next animation frame.
+#[derive(Component)]
+pub struct AnimationTimer(pub Timer);
+
+impl Default for AnimationTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(FRAME_TIME, TimerMode::Repeating))
+    }
+}
+
+/// Atlas layout shared by every walk cycle sheet.
+pub fn walk_cycle_layout() -> TextureAtlasLayout {
+    TextureAtlasLayout::from_grid(UVec2::splat(FRAME_SIZE), WALK_FRAMES, 1, None, None)
+}
+
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, animate_sprites);
+    }
+}
+
+/// Step through the walk cycle of everything that is moving and put the rest
+/// back on their idle frame.
+///
+/// Driven by virtual time, so animations freeze while the game is paused.
+fn animate_sprites(
+    mut sprites: Query<(
+        &AnimationIndices,
+        &mut AnimationTimer,
+        &mut TextureAtlas,
+        &Velocity,
+    )>,
+    time: Res<Time>,
+) {
+    for (indices, mut timer, mut atlas, velocity) in &mut sprites {
+        if velocity.0.length() < MOVING_SPEED {
+            timer.0.reset();
+            atlas.index = indices.idle;
+            continue;
+        }
+
+        timer.0.tick(time.delta());
+
+        if timer.0.just_finished() {
+            atlas.index = if atlas.index >= indices.last || atlas.index < indices.first {
+                indices.first
+            } else {
+                atlas.index + 1
+            };
+        }
+    }
+}
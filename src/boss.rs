@@ -0,0 +1,356 @@
+use std::f32::consts::TAU;
+
+use bevy::math::vec2;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::camera::CameraShake;
+use crate::combat::{apply_stun, AttackDamage, CombatEvent, Dying, Knockback, Stunned};
+use crate::components::{Collidable, Health, MovementSpeed, SpriteFallback};
+use crate::config::GameConfig;
+use crate::effects::{EffectKind, TimedEffects};
+use crate::opponent::{
+    check_victory, spawn_opponent, EnemyType, Opponent, OpponentSprites, SpawnPortal,
+    WaveSpawner,
+};
+use crate::player::Player;
+use crate::state::{GameSet, GameState, HitStop};
+use crate::world::{ArenaSize, WorldBounds};
+
+/// Boss health, as a multiple of the configured opponent health.
+const BOSS_HEALTH: f32 = 8.;
+
+/// Boss collision radius, as a multiple of the configured radius.
+const BOSS_RADIUS: f32 = 2.;
+
+/// Boss movement speed, as a multiple of the configured opponent speed.
+const BOSS_SPEED: f32 = 0.8;
+
+/// Damage of the boss's regular attack, as a multiple of the configured
+/// opponent damage.
+const BOSS_DAMAGE: f32 = 2.;
+
+/// Health fractions below which the boss moves on to its second and third
+/// phase.
+const BOSS_PHASE_THRESHOLDS: [f32; 2] = [2. / 3., 1. / 3.];
+
+/// Seconds the boss stands still and can't be hurt while changing phase.
+const BOSS_TRANSITION_TIME: f32 = 1.2;
+
+/// Seconds between charges from the second phase on.
+const BOSS_CHARGE_INTERVAL: f32 = 3.;
+
+/// Initial speed of a charge. It dies down like any knockback, which carries
+/// the boss roughly a tenth of this far.
+const BOSS_CHARGE_SPEED: f32 = 3000.;
+
+/// Damage dealt by running into the player during a charge.
+const BOSS_CHARGE_DAMAGE: f32 = 25.;
+
+/// Camera trauma added when a charge hits the player.
+const BOSS_CHARGE_TRAUMA: f32 = 0.5;
+
+/// Seconds the game freezes for when a charge hits the player.
+const BOSS_CHARGE_HIT_STOP: f32 = 0.1;
+
+/// Seconds between summons in the third phase.
+const BOSS_SUMMON_INTERVAL: f32 = 6.;
+
+/// Grunts called in by a single summon.
+const BOSS_SUMMON_COUNT: usize = 2;
+
+/// Most other opponents around before the boss stops summoning more.
+const BOSS_MAX_MINIONS: usize = 6;
+
+/// Arena size once the boss starts summoning, closing in on the player for
+/// the end of the fight.
+const BOSS_ARENA_SIZE: Vec2 = Vec2::new(1400., 1000.);
+
+/// What the boss does on top of chasing the player, moving on as its health
+/// drops.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BossPhase {
+    /// Only chases and attacks like any other opponent.
+    #[default]
+    Chase,
+    /// Charges at the player every so often.
+    Charge,
+    /// Summons grunts every so often.
+    Summon,
+}
+
+impl BossPhase {
+    /// Phase for the boss at `fraction` of its health.
+    fn for_health(fraction: f32) -> Self {
+        if fraction > BOSS_PHASE_THRESHOLDS[0] {
+            Self::Chase
+        } else if fraction > BOSS_PHASE_THRESHOLDS[1] {
+            Self::Charge
+        } else {
+            Self::Summon
+        }
+    }
+
+    /// Seconds between the phase's charges or summons.
+    fn action_interval(self) -> f32 {
+        match self {
+            Self::Chase | Self::Charge => BOSS_CHARGE_INTERVAL,
+            Self::Summon => BOSS_SUMMON_INTERVAL,
+        }
+    }
+
+    /// Arena size the fight closes in to for this phase, `None` to leave it.
+    pub fn arena_size(self) -> Option<Vec2> {
+        (self == Self::Summon).then_some(BOSS_ARENA_SIZE)
+    }
+}
+
+/// The climax of a run, an oversized opponent that changes phase as it takes
+/// damage.
+///
+/// Otherwise a regular opponent, so chasing, attacks, collisions, knockback
+/// and stuns all work on it as usual.
+#[derive(Component)]
+pub struct Boss {
+    pub phase: BossPhase,
+    /// Time until the next charge or summon.
+    action: Timer,
+    /// Whether the boss is in a charge that hasn't hit the player yet.
+    charging: bool,
+}
+
+impl Boss {
+    /// Create a boss already in `phase`, with its first action a full
+    /// interval away.
+    pub fn new(phase: BossPhase) -> Self {
+        Self {
+            phase,
+            action: Timer::from_seconds(phase.action_interval(), TimerMode::Repeating),
+            charging: false,
+        }
+    }
+}
+
+/// Sent when the boss moves on to a new phase.
+#[derive(Event)]
+pub struct BossPhaseChanged(pub BossPhase);
+
+/// Whether the boss of this run has shown up yet.
+#[derive(Resource, Default)]
+pub struct BossFight {
+    pub spawned: bool,
+}
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BossFight>()
+            .add_event::<BossPhaseChanged>()
+            .add_systems(OnEnter(GameState::Playing), reset_boss_fight)
+            .add_systems(
+                Update,
+                (
+                    (update_boss_phase, (boss_actions, shrink_arena))
+                        .chain()
+                        .in_set(GameSet::Combat),
+                    // Before the victory check, so clearing the last wave
+                    // brings out the boss instead of ending the run
+                    spawn_boss_after_waves
+                        .before(check_victory)
+                        .in_set(GameSet::Cleanup),
+                ),
+            );
+    }
+}
+
+fn reset_boss_fight(mut fight: ResMut<BossFight>) {
+    fight.spawned = false;
+}
+
+pub fn boss_max_health(config: &GameConfig) -> f32 {
+    config.max_health * BOSS_HEALTH
+}
+
+/// Spawn the boss at `position`, starting in its first phase.
+pub fn spawn_boss(
+    commands: &mut Commands,
+    config: &GameConfig,
+    sprites: &OpponentSprites,
+    bounds: &WorldBounds,
+    position: Vec2,
+) -> Entity {
+    let radius = config.collision_radius * BOSS_RADIUS;
+    let position = bounds.clamp_world_position(position, radius);
+    let boss = spawn_opponent(commands, config, sprites, bounds, EnemyType::Brute, position);
+
+    // Everything else comes from the brute, only the stats are scaled up
+    commands.entity(boss).insert((
+        Boss::new(BossPhase::Chase),
+        Collidable { radius },
+        Health::new(boss_max_health(config)),
+        MovementSpeed(config.opponent_speed * BOSS_SPEED),
+        AttackDamage(config.enemy_attack_damage * BOSS_DAMAGE),
+        SpriteFallback(Color::srgb(0.3, 0.0, 0.0)),
+        Sprite {
+            color: Color::srgb(1.0, 0.4, 0.4),
+            custom_size: Some(Vec2::splat(radius * 2.)),
+            ..default()
+        },
+    ));
+
+    boss
+}
+
+/// Bring in the boss once every wave has been sent and beaten, including the
+/// opponents still to come out of their portals.
+#[allow(clippy::too_many_arguments)]
+fn spawn_boss_after_waves(
+    mut commands: Commands,
+    mut fight: ResMut<BossFight>,
+    opponents: Query<(), With<Opponent>>,
+    portals: Query<(), With<SpawnPortal>>,
+    spawner: Res<WaveSpawner>,
+    sprites: Res<OpponentSprites>,
+    bounds: Res<WorldBounds>,
+    config: Res<GameConfig>,
+) {
+    let cleared = opponents.is_empty() && portals.is_empty();
+
+    if fight.spawned || !spawner.is_finished() || !cleared {
+        return;
+    }
+
+    // From the top edge, like a wave, so it walks in rather than appearing
+    // on top of the player
+    let position = vec2(0., bounds.half_extents().y);
+    spawn_boss(&mut commands, &config, &sprites, &bounds, position);
+    fight.spawned = true;
+    info!("The boss has arrived");
+}
+
+/// Move the boss on to the next phase once its health drops far enough.
+///
+/// Changing phase stuns the boss and makes it invincible for a moment, both
+/// as a telegraph and so a single big hit can't skip a phase. Phases only
+/// ever move forward.
+#[allow(clippy::type_complexity)]
+fn update_boss_phase(
+    mut commands: Commands,
+    mut bosses: Query<
+        (Entity, &mut Boss, &Health, &mut TimedEffects, Option<&mut Stunned>),
+        Without<Dying>,
+    >,
+    mut phase_changes: EventWriter<BossPhaseChanged>,
+) {
+    for (entity, mut boss, health, mut effects, stunned) in &mut bosses {
+        let phase = BossPhase::for_health(health.fraction());
+
+        if phase == boss.phase || health.current <= 0. {
+            continue;
+        }
+
+        *boss = Boss::new(phase);
+        effects.add(EffectKind::Invincible, BOSS_TRANSITION_TIME);
+        apply_stun(&mut commands, entity, stunned, BOSS_TRANSITION_TIME);
+        phase_changes.send(BossPhaseChanged(phase));
+        info!("The boss enters its {phase:?} phase");
+    }
+}
+
+/// Close the arena in once the boss starts summoning.
+fn shrink_arena(
+    mut phase_changes: EventReader<BossPhaseChanged>,
+    mut arena: ResMut<ArenaSize>,
+) {
+    for BossPhaseChanged(phase) in phase_changes.read() {
+        if let Some(size) = phase.arena_size() {
+            arena.set(size);
+        }
+    }
+}
+
+/// Charge at the player or summon grunts, depending on the boss's phase.
+///
+/// A charge is a knockback aimed at the player. It hits the player at most
+/// once, the first time they touch before it dies down.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn boss_actions(
+    mut commands: Commands,
+    mut bosses: Query<
+        (Entity, &mut Boss, &Transform, &Collidable, Has<Knockback>, Has<Stunned>),
+        (Without<Dying>, Without<Player>),
+    >,
+    mut player: Query<(&Transform, &Collidable, &mut Health, &TimedEffects), With<Player>>,
+    minions: Query<(), (With<Opponent>, Without<Boss>)>,
+    sprites: Res<OpponentSprites>,
+    bounds: Res<WorldBounds>,
+    config: Res<GameConfig>,
+    mut shake: ResMut<CameraShake>,
+    mut hit_stop: ResMut<HitStop>,
+    mut combat_events: EventWriter<CombatEvent>,
+    time: Res<Time>,
+) {
+    let Ok((player_transform, player_collidable, mut player_health, effects)) =
+        player.get_single_mut()
+    else {
+        return;
+    };
+
+    let player_position = player_transform.translation.truncate();
+
+    for (entity, mut boss, transform, collidable, knocked, stunned) in &mut bosses {
+        let position = transform.translation.truncate();
+
+        if boss.charging {
+            if !knocked {
+                boss.charging = false;
+            } else if !effects.is_invincible()
+                && collidable.within_reach(position, player_collidable, player_position, 1.)
+            {
+                boss.charging = false;
+                player_health.current = (player_health.current - BOSS_CHARGE_DAMAGE).max(0.);
+                shake.add_trauma(BOSS_CHARGE_TRAUMA);
+                hit_stop.start(BOSS_CHARGE_HIT_STOP);
+                combat_events.send(CombatEvent::PlayerHit(BOSS_CHARGE_DAMAGE));
+            }
+            continue;
+        }
+
+        if stunned || knocked || !boss.action.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        match boss.phase {
+            BossPhase::Chase => {}
+            BossPhase::Charge => {
+                let direction = (player_position - position).normalize_or_zero();
+                commands
+                    .entity(entity)
+                    .insert(Knockback(direction * BOSS_CHARGE_SPEED));
+                boss.charging = true;
+            }
+            BossPhase::Summon => {
+                if minions.iter().count() >= BOSS_MAX_MINIONS {
+                    continue;
+                }
+
+                // Spread evenly around the boss, just clear of its body
+                let distance = collidable.radius + config.collision_radius * 2.;
+
+                for i in 0..BOSS_SUMMON_COUNT {
+                    let angle = TAU * i as f32 / BOSS_SUMMON_COUNT as f32;
+                    let offset = Vec2::from_angle(angle) * distance;
+                    spawn_opponent(
+                        &mut commands,
+                        &config,
+                        &sprites,
+                        &bounds,
+                        EnemyType::Grunt,
+                        position + offset,
+                    );
+                }
+            }
+        }
+    }
+}
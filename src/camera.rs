@@ -0,0 +1,391 @@
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use bevy::render::camera::ClearColorConfig;
+use bevy::render::view::RenderLayers;
+use bevy::window::{PrimaryWindow, WindowMode};
+
+use crate::components::Velocity;
+use crate::player::Player;
+use crate::settings::Settings;
+use crate::state::{GameSet, GameState};
+use crate::world::WorldBounds;
+
+/// Default camera lerp factor, see `Settings`.
+pub const CAM_LERP_FACTOR: f32 = 2.;
+
+/// Default fraction of the current zoom applied per scroll wheel notch, see
+/// `Settings`.
+pub const ZOOM_SPEED: f32 = 0.1;
+
+/// Smallest projection scale, i.e. the furthest the camera zooms in.
+const MIN_ZOOM: f32 = 0.5;
+
+/// Largest projection scale, i.e. the furthest the camera zooms out.
+const MAX_ZOOM: f32 = 3.;
+
+/// Largest camera offset from shaking, reached at full trauma.
+const MAX_SHAKE_OFFSET: f32 = 20.;
+
+/// Trauma lost per second, so a full shake settles in a second.
+const TRAUMA_DECAY: f32 = 1.;
+
+/// Seconds of the player's movement the camera leads by, so at full speed it
+/// looks this far ahead along the way the player is going.
+const LOOK_AHEAD_TIME: f32 = 0.4;
+
+/// Furthest the look-ahead can pull the camera focus from the player.
+const MAX_LOOK_AHEAD: f32 = 120.;
+
+/// How quickly the look-ahead catches up with the player's movement, as an
+/// exponential rate per second. Low enough that stopping eases the camera
+/// back rather than snapping it.
+const LOOK_AHEAD_EASE: f32 = 3.;
+
+/// Key that switches between windowed and borderless fullscreen.
+const FULLSCREEN_KEY: KeyCode = KeyCode::F11;
+
+/// Render layer of the camera drawing the UI. The world stays on the default
+/// layer 0, so nothing in it is ever drawn by the UI camera.
+pub const UI_LAYER: usize = 1;
+
+/// The camera looking at the arena, which follows the player, zooms and
+/// shakes. Only this one should be moved by camera systems.
+#[derive(Component)]
+pub struct WorldCamera;
+
+/// The camera the UI is drawn with, fixed in place on top of the world.
+///
+/// Keeping it separate leaves the world camera free for world only effects,
+/// and zooming or shaking the world never touches the HUD.
+#[derive(Component)]
+pub struct UiCamera;
+
+/// Distance the player can move from the camera focus before it follows.
+#[derive(Resource)]
+pub struct CameraDeadzone {
+    pub radius: f32,
+}
+
+impl Default for CameraDeadzone {
+    fn default() -> Self {
+        Self { radius: 50. }
+    }
+}
+
+/// How much the camera is currently shaking.
+///
+/// `trauma` goes from 0 (still) to 1 (maximum shake); anything can add to it
+/// and it decays on its own. `offset` is the jitter applied last frame, taken
+/// back out before following the player so it never accumulates.
+#[derive(Resource, Default)]
+pub struct CameraShake {
+    pub trauma: f32,
+    offset: Vec2,
+}
+
+impl CameraShake {
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0., 1.);
+    }
+}
+
+/// Offset of the camera focus ahead of the player, eased towards where the
+/// player is heading.
+#[derive(Resource, Default)]
+pub struct CameraLookAhead(pub Vec2);
+
+/// World position under the mouse cursor, as of the last rendered frame.
+#[derive(Resource, Default)]
+pub struct CursorWorld(pub Vec2);
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraDeadzone>()
+            .init_resource::<CursorWorld>()
+            .init_resource::<CameraShake>()
+            .init_resource::<CameraLookAhead>()
+            .add_systems(Startup, setup_camera)
+            // Available everywhere, including menus and while paused
+            .add_systems(Update, toggle_fullscreen)
+            .add_systems(OnEnter(GameState::Playing), reset_camera)
+            .add_systems(
+                Update,
+                (
+                    unshake_camera,
+                    update_camera,
+                    zoom_camera,
+                    clamp_camera,
+                    shake_camera,
+                )
+                    .chain()
+                    .in_set(GameSet::Camera),
+            )
+            .add_systems(
+                PostUpdate,
+                update_cursor_world.after(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+fn setup_camera(mut commands: Commands) {
+    commands.spawn((
+        WorldCamera,
+        Camera2dBundle {
+            camera: Camera {
+                ..default()
+            },
+            ..default()
+        },
+    ));
+
+    // Drawn after the world without clearing it, and picks up every UI node
+    // that doesn't ask for a camera of its own
+    commands.spawn((
+        UiCamera,
+        IsDefaultUiCamera,
+        RenderLayers::layer(UI_LAYER),
+        Camera2dBundle {
+            camera: Camera {
+                order: 1,
+                clear_color: ClearColorConfig::None,
+                ..default()
+            },
+            ..default()
+        },
+    ));
+}
+
+/// Switch the primary window between windowed and borderless fullscreen.
+///
+/// Only the window mode changes. Everything sized from the window, like the
+/// camera clamp and the cursor position, reads the new size next frame, and
+/// the HUD is anchored to the window edges so it follows on its own.
+fn toggle_fullscreen(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    mut window: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !kb_input.just_pressed(FULLSCREEN_KEY) {
+        return;
+    }
+
+    let Ok(mut window) = window.get_single_mut() else {
+        return;
+    };
+
+    window.mode = match window.mode {
+        WindowMode::Windowed => WindowMode::BorderlessFullscreen,
+        _ => WindowMode::Windowed,
+    };
+}
+
+/// Move the camera back to the spawn point at the start of every run.
+fn reset_camera(
+    mut camera: Query<&mut Transform, With<WorldCamera>>,
+    mut shake: ResMut<CameraShake>,
+    mut look_ahead: ResMut<CameraLookAhead>,
+) {
+    *shake = CameraShake::default();
+    *look_ahead = CameraLookAhead::default();
+
+    let Ok(mut camera) = camera.get_single_mut() else {
+        return;
+    };
+
+    camera.translation.x = 0.;
+    camera.translation.y = 0.;
+}
+
+/// Update the camera position by tracking the player.
+///
+/// Runs on real time, so the camera keeps up at the same pace while the game
+/// is slowed down.
+///
+/// The focus leads the player along its velocity, so more of the arena ahead
+/// is in view. The lead goes through the deadzone like any other movement,
+/// and clamping afterwards still keeps the view inside the arena.
+#[allow(clippy::type_complexity)]
+fn update_camera(
+    mut camera: Query<&mut Transform, (With<WorldCamera>, Without<Player>)>,
+    player: Query<(&Transform, &Velocity), (With<Player>, Without<WorldCamera>)>,
+    deadzone: Res<CameraDeadzone>,
+    mut look_ahead: ResMut<CameraLookAhead>,
+    settings: Res<Settings>,
+    time: Res<Time<Real>>,
+) {
+    let Ok(mut camera) = camera.get_single_mut() else {
+        return;
+    };
+
+    let Ok((player, velocity)) = player.get_single() else {
+        return;
+    };
+
+    let target = (velocity.0 * LOOK_AHEAD_TIME).clamp_length_max(MAX_LOOK_AHEAD);
+    let ease = 1. - (-LOOK_AHEAD_EASE * time.delta_seconds()).exp();
+    look_ahead.0 = look_ahead.0.lerp(target, ease);
+
+    let Vec3 { x, y, .. } = player.translation;
+    // Add 150 offset the camera with the player a little
+    let focus = Vec2::new(x, y + 150.) + look_ahead.0;
+    let offset = focus - camera.translation.truncate();
+
+    // Stay put while the player is inside the deadzone
+    if offset.length() <= deadzone.radius {
+        return;
+    }
+
+    // Catch up until the player sits back on the edge of the deadzone
+    let target = focus - offset.normalize() * deadzone.radius;
+    let direction = target.extend(camera.translation.z);
+
+    // Eased like the lead, real time isn't clamped so a long stall could
+    // otherwise overshoot the target
+    let follow = 1. - (-settings.cam_lerp_factor * time.delta_seconds()).exp();
+    camera.translation = camera.translation.lerp(direction, follow);
+}
+
+/// Take out last frame's shake so the follow works from the true position.
+fn unshake_camera(
+    mut camera: Query<&mut Transform, With<WorldCamera>>,
+    mut shake: ResMut<CameraShake>,
+) {
+    let Ok(mut camera) = camera.get_single_mut() else {
+        return;
+    };
+
+    camera.translation -= shake.offset.extend(0.);
+    shake.offset = Vec2::ZERO;
+}
+
+/// Jitter the camera on top of the follow, scaled by the current trauma.
+///
+/// Runs on real time like the follow, so a shake settles just as fast while
+/// the game is slowed down.
+fn shake_camera(
+    mut camera: Query<&mut Transform, With<WorldCamera>>,
+    mut shake: ResMut<CameraShake>,
+    time: Res<Time<Real>>,
+) {
+    let Ok(mut camera) = camera.get_single_mut() else {
+        return;
+    };
+
+    shake.trauma = (shake.trauma - TRAUMA_DECAY * time.delta_seconds()).max(0.);
+
+    if shake.trauma == 0. {
+        return;
+    }
+
+    // Mixing unrelated frequencies gives jitter that looks random without
+    // needing a random number generator
+    let t = time.elapsed_seconds();
+    let noise = Vec2::new(
+        (t * 47.).sin() + (t * 89.).sin() * 0.5,
+        (t * 53.).cos() + (t * 97.).cos() * 0.5,
+    ) / 1.5;
+
+    // Squaring the trauma makes small hits subtle and big ones punchy
+    shake.offset = noise * shake.trauma * shake.trauma * MAX_SHAKE_OFFSET;
+    camera.translation += shake.offset.extend(0.);
+}
+
+/// Zoom the camera in and out with the mouse wheel.
+fn zoom_camera(
+    mut camera: Query<&mut OrthographicProjection, With<WorldCamera>>,
+    mut wheel: EventReader<MouseWheel>,
+    settings: Res<Settings>,
+) {
+    let Ok(mut projection) = camera.get_single_mut() else {
+        return;
+    };
+
+    let scroll: f32 = wheel
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            // Touchpads report pixels, roughly 100 of them make up one notch
+            MouseScrollUnit::Pixel => event.y / 100.,
+        })
+        .sum();
+
+    if scroll == 0. {
+        return;
+    }
+
+    // Only the scale changes; the projection stays centered on the camera so
+    // the view doesn't jump
+    let scale = projection.scale * (1. - scroll * settings.zoom_sensitivity);
+    projection.scale = scale.clamp(MIN_ZOOM, MAX_ZOOM);
+}
+
+/// Keep the view inside the arena, or centered on it along any axis where the
+/// arena is smaller than the view.
+///
+/// Runs after zooming so the visible area is worked out from this frame's
+/// scale. Shake is applied afterwards and may peek past the edge briefly.
+fn clamp_camera(
+    mut camera: Query<(&mut Transform, &OrthographicProjection), With<WorldCamera>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    bounds: Res<WorldBounds>,
+) {
+    let Ok((mut transform, projection)) = camera.get_single_mut() else {
+        return;
+    };
+
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+
+    // The default projection maps one window pixel to one world unit at scale 1
+    let half_view = window.size() / 2. * projection.scale;
+    let limit = bounds.half_extents() - half_view;
+    let position = transform.translation.truncate();
+
+    let clamp_axis = |value: f32, limit: f32| {
+        if limit <= 0. {
+            0.
+        } else {
+            value.clamp(-limit, limit)
+        }
+    };
+
+    transform.translation.x = clamp_axis(position.x, limit.x);
+    transform.translation.y = clamp_axis(position.y, limit.y);
+}
+
+/// Convert the window's cursor position into world coordinates, if the
+/// cursor is inside the window.
+pub fn cursor_world_position(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Option<Vec2> {
+    let cursor = window.cursor_position()?;
+    camera.viewport_to_world_2d(camera_transform, cursor)
+}
+
+/// Track the world position under the cursor.
+///
+/// Runs after transform propagation, so the conversion uses the camera
+/// position that is actually drawn this frame; otherwise the same screen pixel
+/// would lag a frame behind the panning camera.
+fn update_cursor_world(
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), With<WorldCamera>>,
+    mut cursor: ResMut<CursorWorld>,
+) {
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+
+    let Ok((camera, camera_transform)) = camera.get_single() else {
+        return;
+    };
+
+    // Keep the last known position while the cursor is outside the window
+    if let Some(position) = cursor_world_position(window, camera, camera_transform) {
+        cursor.0 = position;
+    }
+}
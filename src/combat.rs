@@ -0,0 +1,1612 @@
+use bevy::prelude::*;
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+use bevy::utils::HashSet;
+
+use crate::camera::{CameraShake, CursorWorld};
+use crate::components::{
+    Collidable, CollisionLayers, Facing, Health, Stamina, StaticBody, Velocity,
+};
+use crate::config::GameConfig;
+use crate::debug::{CollisionCorrections, DebugOverlay};
+use crate::effects::TimedEffects;
+use crate::opponent::Opponent;
+use crate::pickup::Pickup;
+use crate::player::{KeyBindings, Player};
+use crate::spatial::{update_spatial_grid, SpatialGrid};
+use crate::state::{not_hit_stopped, GameClock, GameSet, GameState, GameTimer, HitStop};
+use crate::world::WorldBounds;
+
+/// Extra reach of the player's attack beyond touching distance.
+pub const ATTACK_RANGE: f32 = 20.;
+
+/// Largest angle between the attack facing and an opponent that a swing still
+/// hits, in radians.
+pub const ATTACK_HALF_ARC: f32 = std::f32::consts::FRAC_PI_2;
+
+/// Default health removed from an opponent per attack, see `GameConfig`.
+pub const ATTACK_DAMAGE: f32 = 25.;
+
+/// Seconds before the attack cooldown ends that an attack press is still
+/// remembered, and how long it is kept waiting for the cooldown.
+const ATTACK_BUFFER_WINDOW: f32 = 0.15;
+
+/// Stamina used up by a melee swing.
+const ATTACK_STAMINA_COST: f32 = 15.;
+
+/// Seconds the player has to wait between attacks.
+pub const ATTACK_COOLDOWN: f32 = 0.5;
+
+/// Seconds after a swing that it counts as active, before its recovery starts.
+const ATTACK_ACTIVE_TIME: f32 = 0.1;
+
+/// Seconds the attack key has to be held before releasing it swings a
+/// charged attack instead of a regular one.
+const CHARGE_THRESHOLD: f32 = 0.3;
+
+/// Seconds the attack key has to be held for a fully charged attack.
+const CHARGE_FULL_TIME: f32 = 1.2;
+
+/// Damage multiplier of a fully charged attack.
+const CHARGE_MAX_DAMAGE: f32 = 2.5;
+
+/// Knockback multiplier of a fully charged attack.
+const CHARGE_MAX_KNOCKBACK: f32 = 2.;
+
+/// How far beyond the block ring the charge ring grows at full charge.
+const CHARGE_RING_GROWTH: f32 = 14.;
+
+/// Seconds between projectiles while the fire button is held.
+pub const FIRE_INTERVAL: f32 = 0.2;
+
+/// Speed of the player's projectiles in world units per second.
+const PROJECTILE_SPEED: f32 = 600.;
+
+/// Default health removed from an opponent per projectile.
+pub const PROJECTILE_DAMAGE: f32 = 10.;
+
+/// Collision radius of projectiles.
+const PROJECTILE_RADIUS: f32 = 5.;
+
+/// Initial speed of the shove an opponent gets when hit.
+const KNOCKBACK_STRENGTH: f32 = 800.;
+
+/// How quickly knockback dies down, as an exponential decay rate per second.
+const KNOCKBACK_DECAY: f32 = 10.;
+
+/// Knockback slower than this is dropped entirely.
+const KNOCKBACK_MIN_SPEED: f32 = 20.;
+
+/// Number of passes used to push overlapping collidables apart.
+const COLLISION_ITERATIONS: usize = 4;
+
+/// Default health removed from the player per opponent attack.
+pub const ENEMY_ATTACK_DAMAGE: f32 = 15.;
+
+/// Extra reach of opponent attacks beyond touching distance.
+const ENEMY_ATTACK_RANGE: f32 = 15.;
+
+/// Seconds an opponent telegraphs before its attack lands, long enough to
+/// dash out of.
+const ENEMY_WINDUP: f32 = 0.5;
+
+/// Sprite scale an opponent grows to by the end of its wind up.
+const ENEMY_WINDUP_SCALE: f32 = 1.3;
+
+/// Seconds an opponent waits after attacking before it can wind up again.
+pub const ENEMY_ATTACK_COOLDOWN: f32 = 1.;
+
+/// Camera trauma added per point of damage the player takes.
+const TRAUMA_PER_DAMAGE: f32 = 0.03;
+
+/// Share of incoming damage the player still takes while blocking.
+const BLOCK_DAMAGE_MULTIPLIER: f32 = 0.25;
+
+/// Seconds at the start of a block during which an attack is parried,
+/// negating it entirely and stunning the attacker.
+const PARRY_WINDOW: f32 = 0.15;
+
+/// Seconds a parried opponent stays stunned.
+const PARRY_STUN: f32 = 1.;
+
+/// Damage from a single hit at which the opponent is staggered by it.
+const HEAVY_HIT_DAMAGE: f32 = 20.;
+
+/// Seconds an opponent stays staggered after a heavy hit.
+const HEAVY_HIT_STUN: f32 = 0.4;
+
+/// Color a stunned opponent's sprite is tinted towards.
+const STUN_TINT: Color = Color::srgb(1.0, 0.9, 0.3);
+
+/// How far a stunned opponent's sprite is tinted, from 0 (not at all) to 1.
+const STUN_TINT_AMOUNT: f32 = 0.6;
+
+/// Radius of the ring drawn around the player while blocking, beyond its
+/// collision circle.
+const BLOCK_RING_GAP: f32 = 6.;
+
+/// Seconds the player's area blast takes to charge, from the start of a run
+/// and after every use.
+pub const ULTIMATE_COOLDOWN: f32 = 20.;
+
+/// Reach of the area blast from the edge of the player.
+const ULTIMATE_RANGE: f32 = 220.;
+
+/// Health removed from every opponent caught in the area blast.
+const ULTIMATE_DAMAGE: f32 = 50.;
+
+/// Initial speed of the shove the area blast gives opponents.
+const ULTIMATE_KNOCKBACK: f32 = 1400.;
+
+/// Camera trauma added when the area blast goes off.
+const ULTIMATE_TRAUMA: f32 = 0.4;
+
+/// Seconds the game freezes for when the area blast goes off.
+const ULTIMATE_HIT_STOP: f32 = 0.12;
+
+/// Seconds the game freezes for on a successful parry.
+const PARRY_HIT_STOP: f32 = 0.08;
+
+/// Seconds the area blast's ring takes to expand to its full reach.
+const ULTIMATE_RING_DURATION: f32 = 0.3;
+
+/// Seconds without landing a hit before the combo drops back to zero.
+const COMBO_WINDOW: f32 = 2.;
+
+/// Extra melee damage per hit in the current combo, as a fraction of the
+/// player's base damage.
+const COMBO_DAMAGE_STEP: f32 = 0.1;
+
+/// Largest melee damage multiplier a combo can build up to.
+const COMBO_MAX_MULTIPLIER: f32 = 2.;
+
+/// Seconds a defeated opponent takes to fade out before it is despawned.
+const DEATH_DURATION: f32 = 0.4;
+
+/// Where an entity is in its attack, going by its `AttackCooldown`.
+///
+/// An attack goes from `Ready` through `Windup`, `Active` and `Recovery` back
+/// to `Ready`. Wind ups live outside the cooldown: the player winds up while
+/// holding the attack key, see `ChargingAttack`, and opponents with a
+/// `WindUp`. The swing lands the moment the wind up ends.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttackState {
+    /// Free to attack.
+    Ready,
+    /// Building up to a swing, only ever once the cooldown is over.
+    Windup,
+    /// Just swung, for `ATTACK_ACTIVE_TIME`.
+    Active,
+    /// Waiting out the rest of the cooldown.
+    Recovery,
+}
+
+/// Time remaining until the entity can attack again.
+#[derive(Component)]
+pub struct AttackCooldown {
+    pub timer: GameTimer,
+}
+
+impl AttackCooldown {
+    /// Create a cooldown that is ready to attack straight away.
+    pub fn new(seconds: f32) -> Self {
+        Self {
+            timer: GameTimer::new_finished(seconds, TimerMode::Once),
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.timer.finished()
+    }
+
+    /// The state of the attack, with `winding_up` telling whether the entity
+    /// is building up to its next one.
+    pub fn state(&self, winding_up: bool) -> AttackState {
+        if self.is_ready() && winding_up {
+            AttackState::Windup
+        } else if self.is_ready() {
+            AttackState::Ready
+        } else if self.timer.elapsed_secs() < ATTACK_ACTIVE_TIME {
+            AttackState::Active
+        } else {
+            AttackState::Recovery
+        }
+    }
+
+    /// Whether the attack is within the last `window` seconds of its recovery,
+    /// where it can be cut short with `cancel`.
+    pub fn can_cancel(&self, window: f32) -> bool {
+        self.state(false) == AttackState::Recovery && self.timer.remaining_secs() <= window
+    }
+
+    /// Skip the rest of the cooldown, ready to attack again straight away.
+    pub fn cancel(&mut self) {
+        self.timer.finish();
+    }
+
+    /// Share of the cooldown still left to wait, from 1 right after an attack
+    /// down to 0 once the next one is allowed.
+    pub fn remaining_fraction(&self) -> f32 {
+        self.timer.fraction_remaining()
+    }
+}
+
+/// Limits how often projectiles can be fired.
+#[derive(Component)]
+pub struct FireRate {
+    pub timer: Timer,
+}
+
+impl FireRate {
+    /// Create a fire rate that allows a shot straight away.
+    pub fn new(seconds: f32) -> Self {
+        let mut timer = Timer::from_seconds(seconds, TimerMode::Once);
+        timer.tick(timer.duration());
+        Self { timer }
+    }
+}
+
+/// Charge of the player's area blast, ready to use once the timer finishes.
+#[derive(Component)]
+pub struct UltimateCharge {
+    pub timer: GameTimer,
+}
+
+impl UltimateCharge {
+    /// Create a charge that starts out empty.
+    pub fn new(seconds: f32) -> Self {
+        Self {
+            timer: GameTimer::from_seconds(seconds, TimerMode::Once),
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.timer.finished()
+    }
+
+    /// How far the charge has filled up, from 0 right after a blast to 1 once
+    /// it is ready.
+    pub fn fraction(&self) -> f32 {
+        self.timer.fraction()
+    }
+}
+
+/// Ring expanding out from where an area blast went off, purely visual.
+#[derive(Component)]
+struct UltimateRing {
+    timer: Timer,
+    radius: f32,
+}
+
+/// An attack pressed just before the cooldown ended, fired the moment it
+/// does unless `timer` runs out first.
+#[derive(Component)]
+pub struct BufferedAttack {
+    pub timer: GameTimer,
+}
+
+/// The player is holding the attack key, swinging once it is let go.
+///
+/// Letting go before `CHARGE_THRESHOLD` is a regular attack, anything past it
+/// hits harder the longer it was held, up to `CHARGE_FULL_TIME`.
+#[derive(Component)]
+pub struct ChargingAttack {
+    pub timer: Timer,
+}
+
+impl Default for ChargingAttack {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(CHARGE_FULL_TIME, TimerMode::Once),
+        }
+    }
+}
+
+impl ChargingAttack {
+    /// Whether it has been held long enough to count as a charged attack.
+    pub fn is_charged(&self) -> bool {
+        self.timer.elapsed_secs() >= CHARGE_THRESHOLD
+    }
+
+    /// How far the charge has built up, from 0 at the threshold to 1 at full.
+    pub fn charge(&self) -> f32 {
+        ((self.timer.elapsed_secs() - CHARGE_THRESHOLD) / (CHARGE_FULL_TIME - CHARGE_THRESHOLD))
+            .clamp(0., 1.)
+    }
+}
+
+/// Health removed by each of the entity's melee hits.
+#[derive(Component)]
+pub struct AttackDamage(pub f32);
+
+/// Direction the player's melee swing points at, following the cursor.
+#[derive(Component)]
+pub struct AttackFacing(pub Vec2);
+
+impl Default for AttackFacing {
+    fn default() -> Self {
+        Self(Vec2::X)
+    }
+}
+
+/// The player is holding up a block, present for as long as the right mouse
+/// button is held.
+///
+/// Blocking cuts incoming damage and slows the player down, and the player
+/// can't attack or shoot until it is let go. `timer` runs from the start of
+/// the block and covers the parry window.
+#[derive(Component)]
+pub struct Blocking {
+    pub timer: Timer,
+}
+
+impl Blocking {
+    /// Whether the block is still new enough to parry.
+    pub fn is_parrying(&self) -> bool {
+        !self.timer.finished()
+    }
+}
+
+/// An opponent that can't move or attack until the timer runs out.
+///
+/// Stunning an opponent cancels its wind up. Its sprite is tinted for the
+/// duration, with the color it had before kept to put back afterwards.
+#[derive(Component)]
+pub struct Stunned {
+    pub timer: Timer,
+    base_color: Option<Color>,
+}
+
+impl Stunned {
+    pub fn new(seconds: f32) -> Self {
+        Self {
+            timer: Timer::from_seconds(seconds, TimerMode::Once),
+            base_color: None,
+        }
+    }
+}
+
+/// An opponent telegraphing an attack that lands when the timer runs out.
+#[derive(Component)]
+pub struct WindUp {
+    pub timer: Timer,
+}
+
+/// A defeated opponent fading and shrinking away, despawned once the timer
+/// runs out.
+///
+/// Its `Collidable` is taken away when it starts dying, so it no longer
+/// collides and every attack that looks for collidables passes it by.
+#[derive(Component)]
+pub struct Dying {
+    pub timer: Timer,
+}
+
+/// Velocity an entity is being shoved along, decaying over time.
+#[derive(Component)]
+pub struct Knockback(pub Vec2);
+
+#[derive(Component)]
+pub struct Projectile {
+    pub velocity: Vec2,
+    pub damage: f32,
+}
+
+/// Mesh and material shared by every projectile.
+#[derive(Resource)]
+struct ProjectileAssets {
+    mesh: Mesh2dHandle,
+    material: Handle<ColorMaterial>,
+}
+
+/// Projectiles that hit something or left the arena, kept hidden to be fired
+/// again instead of spawning a new entity for every shot.
+///
+/// A pooled projectile has its `Projectile`, `Collidable` and
+/// `CollisionLayers` removed, which takes it out of every gameplay query and
+/// the spatial grid while it waits.
+#[derive(Resource, Default)]
+struct ProjectilePool {
+    inactive: Vec<Entity>,
+}
+
+impl ProjectilePool {
+    /// Disable `entity` and keep it for the next shot.
+    ///
+    /// The removal only applies once commands run, so a projectile that left
+    /// the arena can still show up in this frame's contacts. Releasing it
+    /// again is ignored rather than pooling it twice.
+    fn release(&mut self, commands: &mut Commands, entity: Entity) {
+        if self.inactive.contains(&entity) {
+            return;
+        }
+
+        commands
+            .entity(entity)
+            .remove::<(Projectile, Collidable, CollisionLayers)>()
+            .insert(Visibility::Hidden);
+        self.inactive.push(entity);
+    }
+}
+
+/// Two collidables that are allowed to interact are overlapping, sent by
+/// `resolve_collisions` for pairs involving a projectile, or by
+/// `move_projectiles` when a projectile's path runs into a target.
+#[derive(Event, Clone, Copy)]
+pub struct Contact(pub Entity, pub Entity);
+
+/// Sent whenever the player swings, whether or not it connects.
+#[derive(Event)]
+pub struct AttackSwung;
+
+/// Sent whenever an opponent takes damage from the player.
+#[derive(Event)]
+pub struct OpponentHit {
+    pub target: Entity,
+    pub damage: f32,
+}
+
+/// Something worth reporting happened in combat, for the combat log.
+#[derive(Event, Clone, Copy)]
+pub enum CombatEvent {
+    EnemyKilled,
+    /// The player took this much damage.
+    PlayerHit(f32),
+    /// An opponent took this much damage.
+    EnemyHit(f32),
+}
+
+/// Number of opponents the player has defeated this run.
+#[derive(Resource, Default)]
+pub struct Score(pub u32);
+
+/// Hits the player landed in a row, each within `COMBO_WINDOW` of the last.
+#[derive(Resource)]
+pub struct Combo {
+    pub count: u32,
+    /// Time since the last hit, the combo ends when it runs out.
+    pub timer: Timer,
+}
+
+impl Default for Combo {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            timer: Timer::from_seconds(COMBO_WINDOW, TimerMode::Once),
+        }
+    }
+}
+
+impl Combo {
+    /// Factor the player's melee damage is scaled by at the current count.
+    pub fn damage_multiplier(&self) -> f32 {
+        (1. + self.count as f32 * COMBO_DAMAGE_STEP).min(COMBO_MAX_MULTIPLIER)
+    }
+}
+
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Contact>()
+            .add_event::<AttackSwung>()
+            .add_event::<OpponentHit>()
+            .add_event::<CombatEvent>()
+            .init_resource::<Score>()
+            .init_resource::<Combo>()
+            .init_resource::<SpatialGrid>()
+            .init_resource::<ProjectilePool>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                (
+                    setup_projectile_assets,
+                    reset_score,
+                    reset_combo,
+                    reset_projectile_pool,
+                ),
+            )
+            .add_systems(
+                FixedUpdate,
+                (apply_knockback, update_spatial_grid, resolve_collisions)
+                    .chain()
+                    .in_set(GameSet::Physics),
+            )
+            .add_systems(
+                Update,
+                (
+                    (
+                        aim_attack,
+                        tick_attack_cooldown,
+                        tick_stun,
+                        regenerate_stamina,
+                        block,
+                        attack,
+                        ultimate,
+                        fire_projectile,
+                        // Projectiles move every frame, so they have to be
+                        // held still here for the freeze
+                        move_projectiles.run_if(not_hit_stopped),
+                        projectile_hits,
+                        update_combo,
+                        enemy_attack,
+                    )
+                        .chain()
+                        .in_set(GameSet::Combat),
+                    (start_dying, fade_dying).chain().in_set(GameSet::Cleanup),
+                    (draw_block_ring, draw_charge_ring, draw_ultimate_rings)
+                        .in_set(GameSet::Ui),
+                ),
+            );
+    }
+}
+
+fn setup_projectile_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(ProjectileAssets {
+        mesh: meshes.add(Circle::new(PROJECTILE_RADIUS)).into(),
+        material: materials.add(Color::srgb(1.0, 1.0, 0.6)),
+    });
+}
+
+/// Every run, whether from the menu or a retry, starts from zero.
+fn reset_score(mut score: ResMut<Score>) {
+    score.0 = 0;
+}
+
+fn reset_combo(mut combo: ResMut<Combo>) {
+    *combo = Combo::default();
+}
+
+/// Forget the last run's pooled projectiles, they were despawned with it.
+fn reset_projectile_pool(mut pool: ResMut<ProjectilePool>) {
+    pool.inactive.clear();
+}
+
+/// Push apart every pair of overlapping collidables.
+///
+/// Static bodies never move, so anything touching one is pushed out by the
+/// full overlap, while two dynamic bodies split it between them. Pushing only
+/// along the contact normal keeps the tangential part of the movement, which
+/// makes bodies slide smoothly around each other.
+///
+/// Any velocity pointing into the contact is removed as well, so bodies don't
+/// keep pushing against what they ran into. Only pairs sharing a cell in the
+/// spatial grid are tested.
+///
+/// Pairs whose `CollisionLayers` don't interact are skipped entirely.
+/// Projectiles never push or get pushed; overlapping one is reported as a
+/// `Contact` instead, for `projectile_hits` to turn into damage.
+///
+/// While the debug overlay is on, every push is recorded in
+/// `CollisionCorrections` for it to draw.
+#[allow(clippy::type_complexity)]
+pub fn resolve_collisions(
+    mut bodies: Query<
+        (
+            Entity,
+            &mut Transform,
+            &Collidable,
+            &CollisionLayers,
+            Has<StaticBody>,
+            Has<Projectile>,
+            Option<&mut Velocity>,
+        ),
+        Without<Pickup>,
+    >,
+    grid: Res<SpatialGrid>,
+    mut contacts: EventWriter<Contact>,
+    overlay: Res<DebugOverlay>,
+    mut corrections: ResMut<CollisionCorrections>,
+) {
+    let pairs = grid.candidate_pairs();
+    corrections.clear();
+
+    // A few passes settle chains of contacts, like being wedged between two bodies
+    for iteration in 0..COLLISION_ITERATIONS {
+        let mut resolved = true;
+
+        for &(a, b) in &pairs {
+            let Ok([a, b]) = bodies.get_many_mut([a, b]) else {
+                continue;
+            };
+            let (a_entity, mut a, a_collidable, a_layers, a_static, a_projectile, a_velocity) = a;
+            let (b_entity, mut b, b_collidable, b_layers, b_static, b_projectile, b_velocity) = b;
+
+            if !a_layers.interacts(b_layers) || (a_static && b_static) {
+                continue;
+            }
+
+            let offset = (a.translation - b.translation).truncate();
+            let distance = offset.length();
+            let min_distance = a_collidable.radius + b_collidable.radius;
+
+            // Small slack so bodies resting exactly on the edge count as resolved
+            if distance >= min_distance - 0.01 {
+                continue;
+            }
+
+            if a_projectile || b_projectile {
+                // Nothing moves, so later passes would only repeat the contact
+                if iteration == 0 {
+                    contacts.send(Contact(a_entity, b_entity));
+                }
+                continue;
+            }
+
+            resolved = false;
+
+            // Pick an arbitrary direction if the centers coincide
+            let normal = offset.try_normalize().unwrap_or(Vec2::X);
+            let push = normal * (min_distance - distance);
+
+            let (a_share, b_share) = match (a_static, b_static) {
+                (true, _) => (0., 1.),
+                (_, true) => (1., 0.),
+                _ => (0.5, 0.5),
+            };
+
+            if overlay.enabled {
+                corrections.record(a_entity, a.translation.truncate(), push * a_share);
+                corrections.record(b_entity, b.translation.truncate(), -push * b_share);
+            }
+
+            a.translation += (push * a_share).extend(0.);
+            b.translation -= (push * b_share).extend(0.);
+
+            // The normal points from b to a, so a moves into the contact against it
+            if let Some(mut velocity) = a_velocity {
+                let into_contact = velocity.0.dot(normal).min(0.);
+                velocity.0 -= normal * into_contact;
+            }
+
+            if let Some(mut velocity) = b_velocity {
+                let into_contact = velocity.0.dot(normal).max(0.);
+                velocity.0 -= normal * into_contact;
+            }
+        }
+
+        if resolved {
+            return;
+        }
+    }
+}
+
+/// Point the player's swing at the cursor.
+fn aim_attack(
+    mut player: Query<(&Transform, &mut AttackFacing), With<Player>>,
+    cursor: Res<CursorWorld>,
+) {
+    let Ok((player_transform, mut facing)) = player.get_single_mut() else {
+        return;
+    };
+
+    // Keep the previous facing while the cursor sits right on the player
+    if let Some(direction) = (cursor.0 - player_transform.translation.truncate()).try_normalize()
+    {
+        facing.0 = direction;
+    }
+}
+
+/// Advance attack cooldowns and ultimate charges, and drop buffered attacks
+/// that waited too long.
+///
+/// Buffers are ticked here rather than in `attack`, so one can't outlast its
+/// window while the player is blocking and fire long after the press.
+/// Everything runs on the `GameClock`, so a press buffered right before a
+/// hit-stop is still waiting once it ends.
+pub fn tick_attack_cooldown(
+    mut commands: Commands,
+    mut cooldowns: Query<&mut AttackCooldown>,
+    mut charges: Query<&mut UltimateCharge>,
+    mut buffered: Query<(Entity, &mut BufferedAttack)>,
+    clock: GameClock,
+) {
+    for mut cooldown in &mut cooldowns {
+        cooldown.timer.tick(&clock);
+    }
+
+    for (entity, mut buffer) in &mut buffered {
+        if buffer.timer.tick(&clock).finished() {
+            commands.entity(entity).remove::<BufferedAttack>();
+        }
+    }
+
+    for mut charge in &mut charges {
+        charge.timer.tick(&clock);
+    }
+}
+
+/// Stun `entity` for `seconds`, or extend a stun it is already under if that
+/// would run out sooner.
+pub fn apply_stun(
+    commands: &mut Commands,
+    entity: Entity,
+    stunned: Option<Mut<Stunned>>,
+    seconds: f32,
+) {
+    match stunned {
+        Some(mut stunned) => {
+            if stunned.timer.remaining_secs() < seconds {
+                stunned.timer = Timer::from_seconds(seconds, TimerMode::Once);
+            }
+        }
+        None => {
+            commands.entity(entity).insert(Stunned::new(seconds));
+        }
+    }
+}
+
+/// Hold stunned opponents in place, tinted, and let them recover once their
+/// stun runs out.
+///
+/// Nothing about the chase is stored while stunned, so once it ends
+/// `move_opponent` simply picks up again from wherever the opponent stands.
+#[allow(clippy::type_complexity)]
+fn tick_stun(
+    mut commands: Commands,
+    mut stunned: Query<(Entity, &mut Stunned, &mut Transform, &mut Velocity, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut stunned, mut transform, mut velocity, mut sprite) in &mut stunned {
+        let base_color = match stunned.base_color {
+            Some(color) => color,
+            None => {
+                // Just stunned, drop any pending attack and stand still
+                let color = sprite.color;
+                stunned.base_color = Some(color);
+                sprite.color = color.mix(&STUN_TINT, STUN_TINT_AMOUNT);
+                transform.scale = Vec3::ONE;
+                velocity.0 = Vec2::ZERO;
+                commands.entity(entity).remove::<WindUp>();
+                color
+            }
+        };
+
+        if stunned.timer.tick(time.delta()).finished() {
+            sprite.color = base_color;
+            commands.entity(entity).remove::<Stunned>();
+        }
+    }
+}
+
+/// Raise a block while the right mouse button is held and drop it on release.
+fn block(
+    mut commands: Commands,
+    mut player: Query<(Entity, Option<&mut Blocking>), With<Player>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    time: Res<Time>,
+) {
+    let Ok((entity, blocking)) = player.get_single_mut() else {
+        return;
+    };
+
+    let held = mouse_input.pressed(MouseButton::Right);
+
+    match blocking {
+        Some(mut blocking) if held => {
+            blocking.timer.tick(time.delta());
+        }
+        Some(_) => {
+            commands.entity(entity).remove::<Blocking>();
+        }
+        // Blocking drops an attack being charged
+        None if held => {
+            commands.entity(entity).remove::<ChargingAttack>();
+            commands.entity(entity).insert(Blocking {
+                timer: Timer::from_seconds(PARRY_WINDOW, TimerMode::Once),
+            });
+        }
+        None => {}
+    }
+}
+
+/// Damage the player actually takes from a hit of `damage`, given how it is
+/// blocking. Parries are handled by the caller, since they also affect the
+/// attacker.
+fn blocked_damage(damage: f32, blocking: Option<&Blocking>) -> f32 {
+    if blocking.is_some() {
+        damage * BLOCK_DAMAGE_MULTIPLIER
+    } else {
+        damage
+    }
+}
+
+/// Draw a ring around the player while it blocks, brighter during the parry
+/// window.
+fn draw_block_ring(mut gizmos: Gizmos, player: Query<(&Transform, &Collidable, &Blocking)>) {
+    let Ok((transform, collidable, blocking)) = player.get_single() else {
+        return;
+    };
+
+    let color = if blocking.is_parrying() {
+        Color::srgb(1.0, 1.0, 0.6)
+    } else {
+        Color::srgb(0.4, 0.7, 1.0)
+    };
+
+    gizmos.circle_2d(
+        transform.translation.truncate(),
+        collidable.radius + BLOCK_RING_GAP,
+        color,
+    );
+}
+
+/// Draw a ring around the player while it charges an attack, growing and
+/// brightening with the charge.
+fn draw_charge_ring(
+    mut gizmos: Gizmos,
+    player: Query<(&Transform, &Collidable, &ChargingAttack)>,
+) {
+    let Ok((transform, collidable, charging)) = player.get_single() else {
+        return;
+    };
+
+    if !charging.is_charged() {
+        return;
+    }
+
+    let charge = charging.charge();
+    let color = if charge >= 1. {
+        Color::srgb(1.0, 0.85, 0.3)
+    } else {
+        Color::srgb(1.0, 0.5, 0.2).with_alpha(0.4 + 0.6 * charge)
+    };
+
+    gizmos.circle_2d(
+        transform.translation.truncate(),
+        collidable.radius + BLOCK_RING_GAP + CHARGE_RING_GROWTH * charge,
+        color,
+    );
+}
+
+/// Refill stamina once the delay after the last spend has passed.
+fn regenerate_stamina(mut stamina: Query<&mut Stamina>, time: Res<Time>) {
+    for mut stamina in &mut stamina {
+        if !stamina.delay.tick(time.delta()).finished() {
+            continue;
+        }
+
+        stamina.current = (stamina.current + stamina.regen * time.delta_seconds()).min(stamina.max);
+    }
+}
+
+/// Hit the nearest opponent in range and in front of the player when the
+/// attack key is released and there is stamina to swing. Not while blocking.
+///
+/// Pressing the key starts a `ChargingAttack`, and holding it long enough
+/// makes the swing hit harder and shove further. A hit heavy enough staggers
+/// the opponent as well as knocking it back. The swing snaps onto the
+/// opponent it hits, turning the player to face it. Damage grows with the
+/// current `Combo`. An attack pressed just before the cooldown ends is
+/// buffered and swings uncharged as soon as it does.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn attack(
+    mut commands: Commands,
+    mut player: Query<
+        (
+            Entity,
+            &Transform,
+            &Collidable,
+            (&mut AttackFacing, &mut Facing),
+            &AttackDamage,
+            &mut AttackCooldown,
+            &mut Stamina,
+            Has<BufferedAttack>,
+            Option<&mut ChargingAttack>,
+        ),
+        (With<Player>, Without<Blocking>),
+    >,
+    mut opponents: Query<
+        (
+            Entity,
+            &Transform,
+            &Collidable,
+            &mut Health,
+            &TimedEffects,
+            Option<&mut Stunned>,
+        ),
+        (With<Opponent>, Without<Player>),
+    >,
+    kb_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut swings: EventWriter<AttackSwung>,
+    mut hits: EventWriter<OpponentHit>,
+    mut combat_events: EventWriter<CombatEvent>,
+    combo: Res<Combo>,
+    time: Res<Time>,
+) {
+    let Ok((
+        player_entity,
+        player_transform,
+        player_collidable,
+        (mut attack_facing, mut facing),
+        damage,
+        mut cooldown,
+        mut stamina,
+        buffered,
+        charging,
+    )) = player.get_single_mut()
+    else {
+        return;
+    };
+
+    let pressed = kb_input.just_pressed(bindings.attack);
+
+    // Zero for a regular swing, up to 1 for a fully charged one
+    let charge = if let Some(mut charging) = charging {
+        charging.timer.tick(time.delta());
+
+        if kb_input.pressed(bindings.attack) {
+            return;
+        }
+
+        commands.entity(player_entity).remove::<ChargingAttack>();
+        charging.charge()
+    } else if !cooldown.is_ready() {
+        // Presses any earlier than the window are dropped as before
+        if pressed && cooldown.timer.remaining_secs() <= ATTACK_BUFFER_WINDOW {
+            commands.entity(player_entity).insert(BufferedAttack {
+                timer: GameTimer::from_seconds(ATTACK_BUFFER_WINDOW, TimerMode::Once),
+            });
+        }
+        return;
+    } else if buffered {
+        // Used up by this swing, or by failing to swing for lack of stamina
+        commands.entity(player_entity).remove::<BufferedAttack>();
+        0.
+    } else {
+        if pressed {
+            commands.entity(player_entity).insert(ChargingAttack::default());
+        }
+        return;
+    };
+
+    if !stamina.try_spend(ATTACK_STAMINA_COST) {
+        return;
+    }
+
+    // A swing starts the cooldown whether or not it connects
+    cooldown.timer.reset();
+    swings.send(AttackSwung);
+
+    let player_position = player_transform.translation.truncate();
+    let min_alignment = ATTACK_HALF_ARC.cos();
+
+    // Only opponents inside the cone in front of the player, anyone standing
+    // right on top of the player counts as in front. Invincible ones can't be
+    // hit, so the swing goes for the next one instead
+    let in_front = opponents
+        .iter()
+        .filter(|(_, _, _, _, effects, _)| !effects.is_invincible())
+        .map(|(entity, transform, collidable, ..)| {
+            (entity, transform.translation.truncate(), collidable)
+        })
+        .filter(|(_, position, _)| {
+            (*position - player_position)
+                .try_normalize()
+                .is_none_or(|direction| direction.dot(attack_facing.0) >= min_alignment)
+        });
+
+    let target = nearest_opponent(player_position, player_collidable, in_front, ATTACK_RANGE);
+
+    if let Some((entity, offset)) = target {
+        let Ok((_, _, _, mut health, _, stunned)) = opponents.get_mut(entity) else {
+            return;
+        };
+
+        // Turn the swing and the player towards whoever it hits
+        if let Some(direction) = offset.try_normalize() {
+            attack_facing.0 = direction;
+            facing.0 = direction;
+        }
+
+        let damage =
+            damage.0 * combo.damage_multiplier() * (1. + (CHARGE_MAX_DAMAGE - 1.) * charge);
+        health.current = (health.current - damage).max(0.);
+        hits.send(OpponentHit {
+            target: entity,
+            damage,
+        });
+        combat_events.send(CombatEvent::EnemyHit(damage));
+
+        if damage >= HEAVY_HIT_DAMAGE {
+            apply_stun(&mut commands, entity, stunned, HEAVY_HIT_STUN);
+        }
+
+        // Shove the opponent directly away from the player
+        let direction = offset.try_normalize().unwrap_or(Vec2::X);
+        let strength = KNOCKBACK_STRENGTH * (1. + (CHARGE_MAX_KNOCKBACK - 1.) * charge);
+        commands
+            .entity(entity)
+            .insert(Knockback(direction * strength));
+    }
+}
+
+/// Closest opponent whose body is within `range` of touching the player, with
+/// its offset from the player.
+///
+/// `opponents` are entity, position and collider triples, so callers can
+/// narrow them down first, like to a cone in front of the player. Ties go to
+/// the lowest entity, so the pick never depends on query order.
+pub fn nearest_opponent<'a>(
+    player_position: Vec2,
+    player_collidable: &Collidable,
+    opponents: impl IntoIterator<Item = (Entity, Vec2, &'a Collidable)>,
+    range: f32,
+) -> Option<(Entity, Vec2)> {
+    opponents
+        .into_iter()
+        .filter(|(_, position, collidable)| {
+            player_collidable.within_reach(player_position, collidable, *position, range)
+        })
+        .map(|(entity, position, _)| (entity, position - player_position))
+        .min_by(|(a_entity, a), (b_entity, b)| {
+            a.length_squared()
+                .total_cmp(&b.length_squared())
+                .then(a_entity.cmp(b_entity))
+        })
+}
+
+/// Set off the area blast when its key is pressed and it is charged, hurting
+/// and shoving away every opponent in reach. Not while blocking.
+///
+/// The player turns to face the nearest opponent caught in the blast.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn ultimate(
+    mut commands: Commands,
+    mut player: Query<
+        (&Transform, &Collidable, &mut UltimateCharge, &mut Facing),
+        (With<Player>, Without<Blocking>),
+    >,
+    mut opponents: Query<
+        (
+            Entity,
+            &Transform,
+            &Collidable,
+            &mut Health,
+            &TimedEffects,
+            Option<&mut Stunned>,
+        ),
+        (With<Opponent>, Without<Player>),
+    >,
+    kb_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut shake: ResMut<CameraShake>,
+    mut hit_stop: ResMut<HitStop>,
+    mut hits: EventWriter<OpponentHit>,
+    mut combat_events: EventWriter<CombatEvent>,
+) {
+    let Ok((player_transform, player_collidable, mut charge, mut facing)) =
+        player.get_single_mut()
+    else {
+        return;
+    };
+
+    if !kb_input.just_pressed(bindings.ultimate) || !charge.is_ready() {
+        return;
+    }
+
+    charge.timer.reset();
+    shake.add_trauma(ULTIMATE_TRAUMA);
+    hit_stop.start(ULTIMATE_HIT_STOP);
+
+    let player_position = player_transform.translation.truncate();
+
+    commands.spawn((
+        UltimateRing {
+            timer: Timer::from_seconds(ULTIMATE_RING_DURATION, TimerMode::Once),
+            radius: player_collidable.radius + ULTIMATE_RANGE,
+        },
+        StateScoped(GameState::Playing),
+        SpatialBundle::from_transform(Transform::from_translation(player_position.extend(0.))),
+    ));
+
+    let nearest = nearest_opponent(
+        player_position,
+        player_collidable,
+        opponents.iter().map(|(entity, transform, collidable, ..)| {
+            (entity, transform.translation.truncate(), collidable)
+        }),
+        ULTIMATE_RANGE,
+    );
+
+    if let Some(direction) = nearest.and_then(|(_, offset)| offset.try_normalize()) {
+        facing.0 = direction;
+    }
+
+    for (entity, transform, collidable, mut health, effects, stunned) in &mut opponents {
+        let position = transform.translation.truncate();
+
+        if effects.is_invincible()
+            || !player_collidable.within_reach(
+                player_position,
+                collidable,
+                position,
+                ULTIMATE_RANGE,
+            )
+        {
+            continue;
+        }
+
+        health.current = (health.current - ULTIMATE_DAMAGE).max(0.);
+        hits.send(OpponentHit {
+            target: entity,
+            damage: ULTIMATE_DAMAGE,
+        });
+        combat_events.send(CombatEvent::EnemyHit(ULTIMATE_DAMAGE));
+        apply_stun(&mut commands, entity, stunned, HEAVY_HIT_STUN);
+
+        let direction = (position - player_position).try_normalize().unwrap_or(Vec2::X);
+        commands
+            .entity(entity)
+            .insert(Knockback(direction * ULTIMATE_KNOCKBACK));
+    }
+}
+
+/// Expand and fade the area blast rings, removing them once they are done.
+fn draw_ultimate_rings(
+    mut commands: Commands,
+    mut gizmos: Gizmos,
+    mut rings: Query<(Entity, &Transform, &mut UltimateRing)>,
+    time: Res<Time>,
+) {
+    for (entity, transform, mut ring) in &mut rings {
+        if ring.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let progress = ring.timer.fraction();
+        gizmos.circle_2d(
+            transform.translation.truncate(),
+            ring.radius * progress,
+            Color::srgba(1.0, 0.8, 0.3, 1. - progress),
+        );
+    }
+}
+
+/// Move entities along their knockback and let it die down over time.
+fn apply_knockback(
+    mut commands: Commands,
+    mut knocked: Query<(Entity, &mut Transform, &mut Knockback)>,
+    time: Res<Time>,
+) {
+    for (entity, mut transform, mut knockback) in &mut knocked {
+        transform.translation += (knockback.0 * time.delta_seconds()).extend(0.);
+        knockback.0 *= (-KNOCKBACK_DECAY * time.delta_seconds()).exp();
+
+        if knockback.0.length() < KNOCKBACK_MIN_SPEED {
+            commands.entity(entity).remove::<Knockback>();
+        }
+    }
+}
+
+/// Keep shooting projectiles from the player towards the cursor while the
+/// left mouse button is held, as fast as the fire rate allows. Not while
+/// blocking.
+#[allow(clippy::too_many_arguments)]
+fn fire_projectile(
+    mut commands: Commands,
+    mut player: Query<(&Transform, &mut FireRate, Has<Blocking>), With<Player>>,
+    cursor: Res<CursorWorld>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    mut pool: ResMut<ProjectilePool>,
+    projectile_assets: Res<ProjectileAssets>,
+    config: Res<GameConfig>,
+    time: Res<Time>,
+) {
+    let Ok((player_transform, mut fire_rate, blocking)) = player.get_single_mut() else {
+        return;
+    };
+
+    // Ticks whether or not the button is held, so the first shot after a
+    // pause in firing is immediate, while letting go and clicking again
+    // can't beat the fire rate
+    fire_rate.timer.tick(time.delta());
+
+    if blocking || !mouse_input.pressed(MouseButton::Left) || !fire_rate.timer.finished() {
+        return;
+    }
+
+    let origin = player_transform.translation.truncate();
+
+    // Clicking right on top of the player has no meaningful direction
+    let Some(direction) = (cursor.0 - origin).try_normalize() else {
+        return;
+    };
+
+    fire_rate.timer.reset();
+
+    let projectile = (
+        Projectile {
+            velocity: direction * PROJECTILE_SPEED,
+            damage: config.projectile_damage,
+        },
+        Collidable {
+            radius: PROJECTILE_RADIUS,
+        },
+        CollisionLayers::PLAYER_PROJECTILE,
+    );
+    let transform = Transform {
+        translation: origin.extend(3.),
+        ..default()
+    };
+
+    // Reuse a pooled projectile if there is one, only growing the pool when
+    // every projectile is in flight. Everything a shot sets is overwritten,
+    // so nothing carries over from its last flight
+    if let Some(entity) = pool.inactive.pop() {
+        commands
+            .entity(entity)
+            .insert((projectile, transform, Visibility::Inherited));
+        return;
+    }
+
+    commands.spawn((
+        StateScoped(GameState::Playing),
+        projectile,
+        MaterialMesh2dBundle {
+            mesh: projectile_assets.mesh.clone(),
+            material: projectile_assets.material.clone(),
+            transform,
+            ..default()
+        },
+    ));
+}
+
+/// Advance projectiles and return the ones that left the arena to the pool.
+#[allow(clippy::type_complexity)]
+fn move_projectiles(
+    mut commands: Commands,
+    mut pool: ResMut<ProjectilePool>,
+    mut projectiles: Query<(Entity, &mut Transform, &Collidable, &CollisionLayers, &Projectile)>,
+    targets: Query<(Entity, &Transform, &Collidable, &CollisionLayers), Without<Projectile>>,
+    bounds: Res<WorldBounds>,
+    time: Res<Time>,
+    mut contacts: EventWriter<Contact>,
+) {
+    let limit = bounds.half_extents();
+
+    for (entity, mut transform, collidable, layers, projectile) in &mut projectiles {
+        let motion = projectile.velocity * time.delta_seconds();
+        let position = transform.translation.truncate();
+
+        // Stop at the first target along the way, a fast projectile could
+        // otherwise pass right over it between two collision checks
+        let hit = targets
+            .iter()
+            .filter(|(_, _, _, target_layers)| layers.interacts(target_layers))
+            .filter_map(|(target, target_transform, target_collidable, _)| {
+                let target_position = target_transform.translation.truncate();
+                collidable
+                    .sweep(position, motion, target_collidable, target_position)
+                    .map(|t| (target, t))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        if let Some((target, t)) = hit {
+            transform.translation += (motion * t).extend(0.);
+            contacts.send(Contact(entity, target));
+            continue;
+        }
+
+        transform.translation += motion.extend(0.);
+
+        let position = transform.translation.truncate();
+        if position.x.abs() > limit.x || position.y.abs() > limit.y {
+            pool.release(&mut commands, entity);
+        }
+    }
+}
+
+/// Damage whatever each projectile touched first and return the projectile to
+/// the pool.
+///
+/// Which targets a projectile can touch at all is decided by its collision
+/// layers in `resolve_collisions`, so this only has to apply the hit. A block
+/// cuts the damage, and a parry stops the projectile without any.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn projectile_hits(
+    mut commands: Commands,
+    mut pool: ResMut<ProjectilePool>,
+    mut contacts: EventReader<Contact>,
+    projectiles: Query<&Projectile>,
+    mut targets: Query<
+        (&mut Health, &TimedEffects, Option<&Blocking>, Has<Opponent>),
+        Without<Projectile>,
+    >,
+    mut shake: ResMut<CameraShake>,
+    mut hit_stop: ResMut<HitStop>,
+    mut hits: EventWriter<OpponentHit>,
+    mut combat_events: EventWriter<CombatEvent>,
+) {
+    // Several fixed steps can report the same projectile before it is pooled
+    let mut spent = HashSet::new();
+
+    for &Contact(a, b) in contacts.read() {
+        let (entity, projectile, target) = match (projectiles.get(a), projectiles.get(b)) {
+            (Ok(projectile), _) => (a, projectile, b),
+            (_, Ok(projectile)) => (b, projectile, a),
+            // Already pooled after an earlier hit
+            _ => continue,
+        };
+
+        if spent.contains(&entity) {
+            continue;
+        }
+
+        match targets.get_mut(target) {
+            // Invincible targets let projectiles pass straight through
+            Ok((_, effects, ..)) if effects.is_invincible() => continue,
+            Ok((_, _, Some(blocking), _)) if blocking.is_parrying() => {
+                hit_stop.start(PARRY_HIT_STOP);
+            }
+            Ok((mut health, _, blocking, is_opponent)) => {
+                let damage = blocked_damage(projectile.damage, blocking);
+                health.current = (health.current - damage).max(0.);
+
+                if is_opponent {
+                    hits.send(OpponentHit { target, damage });
+                    combat_events.send(CombatEvent::EnemyHit(damage));
+                } else {
+                    shake.add_trauma(damage * TRAUMA_PER_DAMAGE);
+                    combat_events.send(CombatEvent::PlayerHit(damage));
+                }
+            }
+            // Anything without health, like an obstacle, just stops it
+            Err(_) => {}
+        }
+
+        spent.insert(entity);
+        pool.release(&mut commands, entity);
+    }
+}
+
+/// Count every hit the player landed this frame towards the combo, and end
+/// the combo once no hit has landed for the whole window.
+///
+/// Any hit counts, melee, projectile or area blast, but only melee damage is
+/// scaled by it.
+fn update_combo(mut combo: ResMut<Combo>, mut hits: EventReader<OpponentHit>, time: Res<Time>) {
+    let landed = hits.read().count() as u32;
+
+    if landed > 0 {
+        combo.count += landed;
+        combo.timer.reset();
+        return;
+    }
+
+    if combo.count > 0 && combo.timer.tick(time.delta()).finished() {
+        combo.count = 0;
+    }
+}
+
+/// Let opponents in reach of the player wind up and then strike.
+///
+/// While winding up an opponent stands still and swells as a telegraph. The
+/// hit only lands if the player is still in reach and not invincible when the
+/// wind up ends, so it can be dodged by dashing. Knockback interrupts the wind
+/// up, and despawning the opponent takes the pending attack with it.
+///
+/// A blocking player takes reduced damage, and one that raised the block just
+/// in time parries instead, taking none and stunning the attacker. Stunned
+/// opponents don't attack at all.
+#[allow(clippy::type_complexity)]
+fn enemy_attack(
+    mut commands: Commands,
+    mut opponents: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Velocity,
+            &Collidable,
+            &mut AttackCooldown,
+            &AttackDamage,
+            Option<&mut WindUp>,
+            Has<Knockback>,
+        ),
+        (With<Opponent>, Without<Player>, Without<Stunned>),
+    >,
+    mut player: Query<
+        (
+            &Transform,
+            &Collidable,
+            &mut Health,
+            &TimedEffects,
+            Option<&Blocking>,
+        ),
+        With<Player>,
+    >,
+    mut shake: ResMut<CameraShake>,
+    mut hit_stop: ResMut<HitStop>,
+    mut combat_events: EventWriter<CombatEvent>,
+    time: Res<Time>,
+) {
+    let Ok((player_transform, player_collidable, mut health, effects, blocking)) =
+        player.get_single_mut()
+    else {
+        return;
+    };
+
+    let player_position = player_transform.translation.truncate();
+
+    for (
+        entity,
+        mut transform,
+        mut velocity,
+        collidable,
+        mut cooldown,
+        attack_damage,
+        wind_up,
+        knocked,
+    ) in &mut opponents
+    {
+        let in_reach = collidable.within_reach(
+            transform.translation.truncate(),
+            player_collidable,
+            player_position,
+            ENEMY_ATTACK_RANGE,
+        );
+
+        let Some(mut wind_up) = wind_up else {
+            if in_reach && !knocked && cooldown.is_ready() {
+                velocity.0 = Vec2::ZERO;
+                commands.entity(entity).insert(WindUp {
+                    timer: Timer::from_seconds(ENEMY_WINDUP, TimerMode::Once),
+                });
+            }
+            continue;
+        };
+
+        if knocked {
+            transform.scale = Vec3::ONE;
+            commands.entity(entity).remove::<WindUp>();
+            continue;
+        }
+
+        wind_up.timer.tick(time.delta());
+        let scale = 1. + (ENEMY_WINDUP_SCALE - 1.) * wind_up.timer.fraction();
+        transform.scale = Vec3::new(scale, scale, 1.);
+
+        if !wind_up.timer.finished() {
+            continue;
+        }
+
+        transform.scale = Vec3::ONE;
+        cooldown.timer.reset();
+        commands.entity(entity).remove::<WindUp>();
+
+        if !in_reach || effects.is_invincible() {
+            continue;
+        }
+
+        if blocking.is_some_and(Blocking::is_parrying) {
+            commands.entity(entity).insert(Stunned::new(PARRY_STUN));
+            hit_stop.start(PARRY_HIT_STOP);
+            continue;
+        }
+
+        let damage = blocked_damage(attack_damage.0, blocking);
+        health.current = (health.current - damage).max(0.);
+        shake.add_trauma(damage * TRAUMA_PER_DAMAGE);
+        combat_events.send(CombatEvent::PlayerHit(damage));
+    }
+}
+
+/// Start the death animation of opponents whose health has run out.
+///
+/// This is the only place kills are counted. Opponents that are already dying
+/// are skipped, so a death is never scored twice.
+#[allow(clippy::type_complexity)]
+fn start_dying(
+    mut commands: Commands,
+    mut opponents: Query<
+        (Entity, &Health, &mut Transform, &mut Velocity),
+        (With<Opponent>, Without<Dying>),
+    >,
+    mut score: ResMut<Score>,
+    mut combat_events: EventWriter<CombatEvent>,
+) {
+    let mut remaining = 0;
+    let mut defeated = false;
+
+    for (entity, health, mut transform, mut velocity) in &mut opponents {
+        if health.current > 0. {
+            remaining += 1;
+            continue;
+        }
+
+        // Settle back from any wind up swell and stand still, which also
+        // puts the walk cycle on its idle frame
+        transform.scale = Vec3::ONE;
+        velocity.0 = Vec2::ZERO;
+
+        commands
+            .entity(entity)
+            .insert(Dying {
+                timer: Timer::from_seconds(DEATH_DURATION, TimerMode::Once),
+            })
+            .remove::<(Collidable, WindUp, Knockback, Stunned)>()
+            // The health bar is a child and would otherwise hang on at zero
+            .despawn_descendants();
+
+        score.0 += 1;
+        combat_events.send(CombatEvent::EnemyKilled);
+        defeated = true;
+    }
+
+    if defeated {
+        info!("Opponent defeated, {remaining} remaining");
+    }
+}
+
+/// Fade out and shrink dying opponents, despawning them at the end.
+fn fade_dying(
+    mut commands: Commands,
+    mut dying: Query<(Entity, &mut Dying, &mut Transform, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut dying, mut transform, mut sprite) in &mut dying {
+        if dying.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let remaining = dying.timer.fraction_remaining();
+        sprite.color.set_alpha(remaining);
+        transform.scale = Vec3::new(remaining, remaining, 1.);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::state::tests::playing_app;
+
+    /// Last seconds of the recovery the attack can be cancelled in.
+    const WINDOW: f32 = 0.2;
+
+    #[test]
+    fn attack_cooldown_steps_through_its_states() {
+        let mut app = playing_app(Duration::from_millis(10));
+        app.add_systems(Update, tick_attack_cooldown);
+
+        let cooldown = AttackCooldown::new(0.5);
+        assert_eq!(cooldown.state(false), AttackState::Ready);
+        assert_eq!(cooldown.state(true), AttackState::Windup);
+        assert!(!cooldown.can_cancel(WINDOW));
+
+        let entity = app.world_mut().spawn(cooldown).id();
+        app.world_mut()
+            .get_mut::<AttackCooldown>(entity)
+            .unwrap()
+            .timer
+            .reset();
+
+        // Checked at a handful of times through the swing, in 10ms frames
+        let mut frames = 0;
+        for (until, expected, cancellable) in [
+            (5, AttackState::Active, false),
+            (15, AttackState::Recovery, false),
+            (25, AttackState::Recovery, false),
+            (35, AttackState::Recovery, true),
+            (45, AttackState::Recovery, true),
+            (55, AttackState::Ready, false),
+        ] {
+            while frames < until {
+                app.update();
+                frames += 1;
+            }
+
+            let cooldown = app.world().get::<AttackCooldown>(entity).unwrap();
+            assert_eq!(cooldown.state(false), expected, "after {frames} frames");
+            assert_eq!(cooldown.can_cancel(WINDOW), cancellable, "after {frames} frames");
+            // A wind up can't begin before the cooldown is over
+            assert_eq!(cooldown.state(true) == AttackState::Windup, expected == AttackState::Ready);
+        }
+    }
+}
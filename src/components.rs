@@ -0,0 +1,273 @@
+use std::ops::BitOr;
+
+use bevy::prelude::*;
+
+/// Default collision radius for both player and opponent, see `GameConfig`.
+pub const COLLISION_RADIUS: f32 = 25.;
+
+/// Default starting (and maximum) health for both player and opponent.
+pub const MAX_HEALTH: f32 = 100.;
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Collidable {
+    pub radius: f32,
+}
+
+impl Collidable {
+    /// Whether this body at `position` is within `range` of touching `other`
+    /// at `other_position`. Every range check in combat goes through this.
+    pub fn within_reach(
+        &self,
+        position: Vec2,
+        other: &Collidable,
+        other_position: Vec2,
+        range: f32,
+    ) -> bool {
+        position.distance(other_position) <= self.radius + other.radius + range
+    }
+
+    /// How far along `motion` this body, starting at `position`, first touches
+    /// `other` at `other_position`, as a fraction from 0 to 1.
+    ///
+    /// Fast movers use this to stop at the first contact instead of jumping
+    /// over a target between two steps. Bodies that already overlap give
+    /// `None` and are left to the collision resolver, so they can still move
+    /// apart.
+    pub fn sweep(
+        &self,
+        position: Vec2,
+        motion: Vec2,
+        other: &Collidable,
+        other_position: Vec2,
+    ) -> Option<f32> {
+        let offset = position - other_position;
+        let radius = self.radius + other.radius;
+
+        // Solve |offset + motion * t| = radius for the earliest t
+        let a = motion.length_squared();
+        let b = 2. * offset.dot(motion);
+        let c = offset.length_squared() - radius * radius;
+
+        if a == 0. || c <= 0. {
+            return None;
+        }
+
+        let discriminant = b * b - 4. * a * c;
+        if discriminant < 0. {
+            return None;
+        }
+
+        let t = (-b - discriminant.sqrt()) / (2. * a);
+        (0. ..=1.).contains(&t).then_some(t)
+    }
+}
+
+/// Set of collision layers as bit flags.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CollisionLayer(u8);
+
+impl CollisionLayer {
+    pub const PLAYER: Self = Self(1 << 0);
+    pub const ENEMY: Self = Self(1 << 1);
+    pub const PROJECTILE: Self = Self(1 << 2);
+    pub const OBSTACLE: Self = Self(1 << 3);
+    pub const ALL: Self = Self(u8::MAX);
+
+    /// Both sets combined. The same as `|`, but usable in constants.
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for CollisionLayer {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        self.union(other)
+    }
+}
+
+/// Which layers a collidable is on, and which layers it interacts with.
+///
+/// Two collidables only touch when each one's mask includes a layer the other
+/// is on. The collision resolver checks this for every pair, so no other
+/// system needs to decide what may hit what.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct CollisionLayers {
+    pub memberships: CollisionLayer,
+    pub mask: CollisionLayer,
+}
+
+impl CollisionLayers {
+    pub const PLAYER: Self = Self::new(
+        CollisionLayer::PLAYER,
+        CollisionLayer::ENEMY
+            .union(CollisionLayer::PROJECTILE)
+            .union(CollisionLayer::OBSTACLE),
+    );
+
+    pub const ENEMY: Self = Self::new(
+        CollisionLayer::ENEMY,
+        CollisionLayer::PLAYER
+            .union(CollisionLayer::ENEMY)
+            .union(CollisionLayer::PROJECTILE)
+            .union(CollisionLayer::OBSTACLE),
+    );
+
+    pub const OBSTACLE: Self = Self::new(CollisionLayer::OBSTACLE, CollisionLayer::ALL);
+
+    /// A projectile fired by the player, passing through the player.
+    pub const PLAYER_PROJECTILE: Self = Self::new(
+        CollisionLayer::PROJECTILE,
+        CollisionLayer::ENEMY.union(CollisionLayer::OBSTACLE),
+    );
+
+    /// A projectile fired by an opponent, passing through other opponents.
+    // No opponent shoots yet
+    #[allow(dead_code)]
+    pub const ENEMY_PROJECTILE: Self = Self::new(
+        CollisionLayer::PROJECTILE,
+        CollisionLayer::PLAYER.union(CollisionLayer::OBSTACLE),
+    );
+
+    pub const fn new(memberships: CollisionLayer, mask: CollisionLayer) -> Self {
+        Self { memberships, mask }
+    }
+
+    /// Whether these two collidables should touch at all.
+    pub fn interacts(&self, other: &Self) -> bool {
+        self.mask.intersects(other.memberships) && other.mask.intersects(self.memberships)
+    }
+}
+
+/// Marks a collidable that is never pushed by collisions, like a wall.
+#[derive(Component)]
+pub struct StaticBody;
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self { current: max, max }
+    }
+
+    /// Remaining health between 0 and 1, treating a zero max as empty.
+    pub fn fraction(&self) -> f32 {
+        if self.max <= 0. {
+            return 0.;
+        }
+
+        (self.current / self.max).clamp(0., 1.)
+    }
+}
+
+/// Movement speed in world units per second.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct MovementSpeed(pub f32);
+
+/// Current velocity in world units per second.
+#[derive(Component, Default)]
+pub struct Velocity(pub Vec2);
+
+/// Unit direction the entity is facing, kept while it stands still.
+#[derive(Component)]
+pub struct Facing(pub Vec2);
+
+impl Default for Facing {
+    fn default() -> Self {
+        Self(Vec2::X)
+    }
+}
+
+/// Resource spent on dashing and attacking, refilling over time.
+///
+/// Refilling waits for `delay` after anything is spent, so actions can't be
+/// chained forever by spending as fast as it comes back.
+#[derive(Component)]
+pub struct Stamina {
+    pub current: f32,
+    pub max: f32,
+    /// Stamina regained per second.
+    pub regen: f32,
+    pub delay: Timer,
+}
+
+impl Stamina {
+    pub fn new(max: f32, regen: f32, delay: f32) -> Self {
+        let mut delay = Timer::from_seconds(delay, TimerMode::Once);
+        delay.tick(delay.duration());
+
+        Self {
+            current: max,
+            max,
+            regen,
+            delay,
+        }
+    }
+
+    /// Spend `cost` if there is enough, returning whether it was spent.
+    pub fn try_spend(&mut self, cost: f32) -> bool {
+        if self.current < cost {
+            return false;
+        }
+
+        self.current -= cost;
+        self.delay.reset();
+        true
+    }
+
+    /// Remaining stamina between 0 and 1, treating a zero max as empty.
+    pub fn fraction(&self) -> f32 {
+        if self.max <= 0. {
+            return 0.;
+        }
+
+        (self.current / self.max).clamp(0., 1.)
+    }
+}
+
+/// Color drawn in place of the sprite when its texture is missing.
+#[derive(Component)]
+pub struct SpriteFallback(pub Color);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: Collidable = Collidable { radius: 10. };
+
+    #[test]
+    fn sweep_hits_a_body_passed_through_in_one_step() {
+        // Ends the step well past the other body, never overlapping at either end
+        let t = BODY.sweep(Vec2::ZERO, Vec2::new(200., 0.), &BODY, Vec2::new(100., 0.));
+
+        // First touches 20 short of the other body's center
+        let t = t.expect("sweep missed a body in its path");
+        assert!((t - 80. / 200.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn sweep_misses_a_body_off_to_the_side() {
+        let t = BODY.sweep(Vec2::ZERO, Vec2::new(200., 0.), &BODY, Vec2::new(100., 30.));
+
+        assert_eq!(t, None);
+    }
+
+    #[test]
+    fn sweep_leaves_overlapping_bodies_alone() {
+        let t = BODY.sweep(Vec2::ZERO, Vec2::new(200., 0.), &BODY, Vec2::new(5., 0.));
+
+        assert_eq!(t, None);
+    }
+}
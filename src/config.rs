@@ -0,0 +1,225 @@
+use std::fs;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::combat::{ATTACK_DAMAGE, ENEMY_ATTACK_DAMAGE, PROJECTILE_DAMAGE};
+use crate::components::{COLLISION_RADIUS, MAX_HEALTH};
+use crate::difficulty::{
+    DIFFICULTY_HEALTH_STEP, DIFFICULTY_MAX_TIER, DIFFICULTY_SPAWN_STEP, DIFFICULTY_SPEED_STEP,
+    DIFFICULTY_TIER_TIME,
+};
+use crate::opponent::OPPONENT_SPEED;
+use crate::player::{
+    DASH_CANCEL_WINDOW, HEALTH_REGEN_DELAY, HEALTH_REGEN_RATE, PLAYER_SPEED, XP_GROWTH,
+    XP_PER_KILL, XP_TO_LEVEL,
+};
+use crate::state::MAX_FRAME_DELTA;
+
+/// Balance file read at startup, relative to the working directory.
+const CONFIG_PATH: &str = "config.ron";
+
+/// Balance values that can be tweaked in `config.ron` without recompiling.
+///
+/// Any field left out of the file keeps its compiled in default.
+#[derive(Resource, Deserialize, Debug)]
+#[serde(default)]
+pub struct GameConfig {
+    pub player_speed: f32,
+    pub opponent_speed: f32,
+    pub collision_radius: f32,
+    pub max_health: f32,
+    pub attack_damage: f32,
+    pub projectile_damage: f32,
+    pub enemy_attack_damage: f32,
+    /// Experience the player earns per opponent defeated.
+    pub xp_per_kill: f32,
+    /// Experience needed to reach level 2.
+    pub xp_to_level: f32,
+    /// Factor the experience needed grows by with every level.
+    pub xp_growth: f32,
+    /// Longest time in seconds a single frame can advance the game by, so a
+    /// lag spike slows the game down instead of making things jump.
+    pub max_frame_delta: f32,
+    /// Health the player regains per second once out of combat.
+    pub health_regen_rate: f32,
+    /// Seconds the player has to go without getting hit before health starts
+    /// refilling.
+    pub health_regen_delay: f32,
+    /// Seconds at the end of an attack's recovery that dashing cancels the
+    /// rest of. Zero turns dash cancels off.
+    pub dash_cancel_window: f32,
+    /// Seconds the player has to survive for the difficulty to go up a tier.
+    pub difficulty_tier_time: f32,
+    /// Extra health of newly arriving opponents per difficulty tier, as a
+    /// share of their base health.
+    pub difficulty_health_step: f32,
+    /// Extra speed of newly arriving opponents per difficulty tier, as a
+    /// share of their base speed.
+    pub difficulty_speed_step: f32,
+    /// Extra rate of waves per difficulty tier, as a share of the base rate.
+    pub difficulty_spawn_step: f32,
+    /// Highest difficulty tier, counted from zero, after which nothing gets
+    /// any harder.
+    pub difficulty_max_tier: u32,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            player_speed: PLAYER_SPEED,
+            opponent_speed: OPPONENT_SPEED,
+            collision_radius: COLLISION_RADIUS,
+            max_health: MAX_HEALTH,
+            attack_damage: ATTACK_DAMAGE,
+            projectile_damage: PROJECTILE_DAMAGE,
+            enemy_attack_damage: ENEMY_ATTACK_DAMAGE,
+            xp_per_kill: XP_PER_KILL,
+            xp_to_level: XP_TO_LEVEL,
+            xp_growth: XP_GROWTH,
+            max_frame_delta: MAX_FRAME_DELTA,
+            health_regen_rate: HEALTH_REGEN_RATE,
+            health_regen_delay: HEALTH_REGEN_DELAY,
+            dash_cancel_window: DASH_CANCEL_WINDOW,
+            difficulty_tier_time: DIFFICULTY_TIER_TIME,
+            difficulty_health_step: DIFFICULTY_HEALTH_STEP,
+            difficulty_speed_step: DIFFICULTY_SPEED_STEP,
+            difficulty_spawn_step: DIFFICULTY_SPAWN_STEP,
+            difficulty_max_tier: DIFFICULTY_MAX_TIER,
+        }
+    }
+}
+
+impl GameConfig {
+    /// Read the config from `path`, falling back to the defaults if the file
+    /// is missing or can't be parsed.
+    pub fn load(path: &str) -> Self {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                info!("No config loaded from {path} ({err}), using defaults");
+                return Self::default();
+            }
+        };
+
+        match ron::from_str::<Self>(&text) {
+            Ok(config) => config.validated(),
+            Err(err) => {
+                warn!("Invalid config in {path}, using defaults: {err}");
+                Self::default()
+            }
+        }
+    }
+
+    /// Replace any value that would break the game with its default.
+    fn validated(self) -> Self {
+        let defaults = Self::default();
+
+        Self {
+            player_speed: positive("player_speed", self.player_speed, defaults.player_speed),
+            opponent_speed: positive(
+                "opponent_speed",
+                self.opponent_speed,
+                defaults.opponent_speed,
+            ),
+            collision_radius: positive(
+                "collision_radius",
+                self.collision_radius,
+                defaults.collision_radius,
+            ),
+            max_health: positive("max_health", self.max_health, defaults.max_health),
+            attack_damage: positive("attack_damage", self.attack_damage, defaults.attack_damage),
+            projectile_damage: positive(
+                "projectile_damage",
+                self.projectile_damage,
+                defaults.projectile_damage,
+            ),
+            enemy_attack_damage: positive(
+                "enemy_attack_damage",
+                self.enemy_attack_damage,
+                defaults.enemy_attack_damage,
+            ),
+            xp_per_kill: positive("xp_per_kill", self.xp_per_kill, defaults.xp_per_kill),
+            xp_to_level: positive("xp_to_level", self.xp_to_level, defaults.xp_to_level),
+            // Shrinking requirements could make a single kill worth endless levels
+            xp_growth: at_least("xp_growth", self.xp_growth, 1., defaults.xp_growth),
+            max_frame_delta: positive(
+                "max_frame_delta",
+                self.max_frame_delta,
+                defaults.max_frame_delta,
+            ),
+            health_regen_rate: positive(
+                "health_regen_rate",
+                self.health_regen_rate,
+                defaults.health_regen_rate,
+            ),
+            health_regen_delay: positive(
+                "health_regen_delay",
+                self.health_regen_delay,
+                defaults.health_regen_delay,
+            ),
+            dash_cancel_window: at_least(
+                "dash_cancel_window",
+                self.dash_cancel_window,
+                0.,
+                defaults.dash_cancel_window,
+            ),
+            difficulty_tier_time: positive(
+                "difficulty_tier_time",
+                self.difficulty_tier_time,
+                defaults.difficulty_tier_time,
+            ),
+            difficulty_health_step: at_least(
+                "difficulty_health_step",
+                self.difficulty_health_step,
+                0.,
+                defaults.difficulty_health_step,
+            ),
+            difficulty_speed_step: at_least(
+                "difficulty_speed_step",
+                self.difficulty_speed_step,
+                0.,
+                defaults.difficulty_speed_step,
+            ),
+            difficulty_spawn_step: at_least(
+                "difficulty_spawn_step",
+                self.difficulty_spawn_step,
+                0.,
+                defaults.difficulty_spawn_step,
+            ),
+            difficulty_max_tier: self.difficulty_max_tier,
+        }
+    }
+}
+
+/// `value` if it is a usable positive number, otherwise `default`.
+fn positive(name: &str, value: f32, default: f32) -> f32 {
+    if value.is_finite() && value > 0. {
+        return value;
+    }
+
+    warn!("Config value {name} must be positive, got {value}, using {default}");
+    default
+}
+
+/// `value` if it is a number no smaller than `min`, otherwise `default`.
+fn at_least(name: &str, value: f32, min: f32, default: f32) -> f32 {
+    if value.is_finite() && value >= min {
+        return value;
+    }
+
+    warn!("Config value {name} must be at least {min}, got {value}, using {default}");
+    default
+}
+
+pub struct ConfigPlugin;
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        // Loaded right away rather than in a system, so the config is in place
+        // before anything spawns
+        let config = GameConfig::load(CONFIG_PATH);
+        info!("Loaded {config:?}");
+        app.insert_resource(config);
+    }
+}
@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::combat::{AttackFacing, ATTACK_HALF_ARC, ATTACK_RANGE};
+use crate::components::{Collidable, Facing};
+use crate::player::Player;
+
+/// Key that shows and hides the debug overlay.
+const DEBUG_TOGGLE_KEY: KeyCode = KeyCode::F3;
+
+/// How much longer than the actual correction its arrow is drawn, since most
+/// corrections are only a few units long.
+const CORRECTION_ARROW_SCALE: f32 = 4.;
+
+/// Whether collision and attack shapes are drawn over the game.
+#[derive(Resource, Default)]
+pub struct DebugOverlay {
+    pub enabled: bool,
+}
+
+/// Push-outs applied by the latest collision step, so the overlay can show
+/// what the resolver did.
+///
+/// Only filled in while the overlay is on. Each entity maps to where it was
+/// before its first push, and the sum of every push it was given.
+#[derive(Resource, Default)]
+pub struct CollisionCorrections {
+    corrections: HashMap<Entity, (Vec2, Vec2)>,
+}
+
+impl CollisionCorrections {
+    pub fn clear(&mut self) {
+        self.corrections.clear();
+    }
+
+    /// Add a push of `correction` given to `entity` while it was at `position`.
+    /// Static bodies are never moved and are left out.
+    pub fn record(&mut self, entity: Entity, position: Vec2, correction: Vec2) {
+        if correction == Vec2::ZERO {
+            return;
+        }
+
+        self.corrections
+            .entry(entity)
+            .or_insert((position, Vec2::ZERO))
+            .1 += correction;
+    }
+}
+
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DebugOverlay>()
+            .init_resource::<CollisionCorrections>()
+            .add_systems(
+            Update,
+            (
+                toggle_debug_overlay,
+                // Skipped entirely while the overlay is off
+                draw_debug_gizmos.run_if(overlay_enabled),
+            )
+                .chain(),
+        );
+    }
+}
+
+fn overlay_enabled(overlay: Res<DebugOverlay>) -> bool {
+    overlay.enabled
+}
+
+fn toggle_debug_overlay(kb_input: Res<ButtonInput<KeyCode>>, mut overlay: ResMut<DebugOverlay>) {
+    if kb_input.just_pressed(DEBUG_TOGGLE_KEY) {
+        overlay.enabled = !overlay.enabled;
+    }
+}
+
+/// Outline every collidable, show which way the player faces and where its
+/// swing reaches, and point out how the last collision step pushed bodies
+/// apart.
+fn draw_debug_gizmos(
+    mut gizmos: Gizmos,
+    collidables: Query<(&Transform, &Collidable)>,
+    player: Query<(&Transform, &Collidable, &Facing, &AttackFacing), With<Player>>,
+    corrections: Res<CollisionCorrections>,
+) {
+    for (transform, collidable) in &collidables {
+        gizmos.circle_2d(
+            transform.translation.truncate(),
+            collidable.radius,
+            Color::srgb(1.0, 1.0, 0.0),
+        );
+    }
+
+    for &(position, correction) in corrections.corrections.values() {
+        gizmos.arrow_2d(
+            position,
+            position + correction * CORRECTION_ARROW_SCALE,
+            Color::srgb(1.0, 0.0, 1.0),
+        );
+    }
+
+    let Ok((transform, collidable, facing, attack_facing)) = player.get_single() else {
+        return;
+    };
+
+    let position = transform.translation.truncate();
+
+    gizmos.line_2d(
+        position,
+        position + facing.0 * collidable.radius * 2.,
+        Color::srgb(0.0, 1.0, 1.0),
+    );
+
+    // Arcs are measured counter-clockwise from +Y rather than +X. The
+    // reach shown is against an opponent of the player's size
+    let direction_angle = attack_facing.0.to_angle() - std::f32::consts::FRAC_PI_2;
+    gizmos.arc_2d(
+        position,
+        direction_angle,
+        ATTACK_HALF_ARC * 2.,
+        collidable.radius * 2. + ATTACK_RANGE,
+        Color::srgb(1.0, 0.3, 0.3),
+    );
+}
@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use bevy::time::Stopwatch;
+
+use crate::config::GameConfig;
+use crate::state::{GameSet, GameState};
+
+/// Default seconds survived per difficulty tier, see `GameConfig`.
+pub const DIFFICULTY_TIER_TIME: f32 = 30.;
+
+/// Default extra opponent health per tier, as a share of their base health,
+/// see `GameConfig`.
+pub const DIFFICULTY_HEALTH_STEP: f32 = 0.15;
+
+/// Default extra opponent speed per tier, as a share of their base speed, see
+/// `GameConfig`.
+pub const DIFFICULTY_SPEED_STEP: f32 = 0.05;
+
+/// Default extra rate of waves per tier, as a share of the base rate, see
+/// `GameConfig`.
+pub const DIFFICULTY_SPAWN_STEP: f32 = 0.1;
+
+/// Default highest difficulty tier, see `GameConfig`.
+pub const DIFFICULTY_MAX_TIER: u32 = 8;
+
+/// How hard the run has become, going up a tier every so often the player
+/// survives.
+///
+/// Only opponents arriving with a wave are scaled, the ones already in the
+/// arena keep their stats. The curve itself comes from `GameConfig`.
+#[derive(Resource, Default)]
+pub struct Difficulty {
+    /// Time survived this run, not counting time spent paused.
+    pub elapsed: Stopwatch,
+    /// Starts at zero, where nothing is scaled.
+    pub tier: u32,
+}
+
+impl Difficulty {
+    /// Factor new opponents' health is scaled by.
+    pub fn health_multiplier(&self, config: &GameConfig) -> f32 {
+        1. + config.difficulty_health_step * self.tier as f32
+    }
+
+    /// Factor new opponents' movement speed is scaled by.
+    pub fn speed_multiplier(&self, config: &GameConfig) -> f32 {
+        1. + config.difficulty_speed_step * self.tier as f32
+    }
+
+    /// Factor the wave timer runs faster by.
+    pub fn spawn_rate_multiplier(&self, config: &GameConfig) -> f32 {
+        1. + config.difficulty_spawn_step * self.tier as f32
+    }
+}
+
+pub struct DifficultyPlugin;
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Difficulty>()
+            .add_systems(OnEnter(GameState::Playing), reset_difficulty)
+            .add_systems(Update, scale_difficulty.in_set(GameSet::Combat));
+    }
+}
+
+/// Start every run back at the lowest tier.
+fn reset_difficulty(mut difficulty: ResMut<Difficulty>) {
+    *difficulty = Difficulty::default();
+}
+
+/// Count up the time survived and move the tier along with it.
+fn scale_difficulty(
+    mut difficulty: ResMut<Difficulty>,
+    config: Res<GameConfig>,
+    time: Res<Time>,
+) {
+    difficulty.elapsed.tick(time.delta());
+
+    let tier = (difficulty.elapsed.elapsed_secs() / config.difficulty_tier_time) as u32;
+    let tier = tier.min(config.difficulty_max_tier);
+
+    if tier != difficulty.tier {
+        difficulty.tier = tier;
+        info!("Difficulty rises to tier {}", tier + 1);
+    }
+}
@@ -0,0 +1,333 @@
+use bevy::prelude::*;
+
+use crate::camera::{CursorWorld, WorldCamera};
+use crate::components::Collidable;
+use crate::state::GameState;
+use crate::world::{
+    spawn_arena_floor, spawn_obstacle, Obstacle, ObstacleLayout, ObstacleSpec, WorldBounds,
+    LAYOUT_PATH, OBSTACLE_COLOR,
+};
+
+/// Spacing of the grid obstacles snap to, in world units.
+const GRID_SIZE: f32 = 50.;
+
+/// Radius of newly placed obstacles until it is changed.
+const BRUSH_RADIUS: f32 = 40.;
+
+/// Amount a single press changes the brush radius by.
+const BRUSH_RADIUS_STEP: f32 = 10.;
+
+/// Smallest and largest obstacle radius the brush allows.
+const BRUSH_RADIUS_RANGE: (f32, f32) = (20., 200.);
+
+/// Speed the editor camera pans at, in world units per second.
+const EDITOR_PAN_SPEED: f32 = 600.;
+
+/// Key that writes the layout to `LAYOUT_PATH`, the same one that saves a run.
+const SAVE_LAYOUT_KEY: KeyCode = KeyCode::F5;
+
+/// Key that throws away unsaved changes and reads `LAYOUT_PATH` again.
+const LOAD_LAYOUT_KEY: KeyCode = KeyCode::F9;
+
+/// Keys that shrink and grow the brush.
+const SHRINK_BRUSH_KEY: KeyCode = KeyCode::BracketLeft;
+const GROW_BRUSH_KEY: KeyCode = KeyCode::BracketRight;
+
+/// Size of the obstacles placed by clicking.
+#[derive(Resource)]
+struct EditorBrush {
+    radius: f32,
+}
+
+impl Default for EditorBrush {
+    fn default() -> Self {
+        Self {
+            radius: BRUSH_RADIUS,
+        }
+    }
+}
+
+/// Material shared by the obstacles placed in the editor.
+#[derive(Resource)]
+struct EditorAssets {
+    obstacle_material: Handle<ColorMaterial>,
+}
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EditorBrush>()
+            .add_systems(OnEnter(GameState::Editor), setup_editor)
+            .add_systems(
+                Update,
+                (
+                    pan_editor_camera,
+                    resize_brush,
+                    (place_obstacle, remove_obstacle, save_layout, load_layout).chain(),
+                    draw_editor_grid,
+                    leave_editor,
+                )
+                    .run_if(in_state(GameState::Editor)),
+            );
+    }
+}
+
+/// Position of the grid point closest to `position`.
+fn snap_to_grid(position: Vec2) -> Vec2 {
+    (position / GRID_SIZE).round() * GRID_SIZE
+}
+
+/// Lay out the arena and the current layout, with the camera back in the
+/// middle.
+fn setup_editor(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut camera: Query<&mut Transform, With<WorldCamera>>,
+    bounds: Res<WorldBounds>,
+    layout: Res<ObstacleLayout>,
+) {
+    spawn_arena_floor(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        &bounds,
+        GameState::Editor,
+    );
+
+    let obstacle_material = materials.add(OBSTACLE_COLOR);
+
+    for obstacle in &layout.obstacles {
+        spawn_obstacle(
+            &mut commands,
+            &mut meshes,
+            &obstacle_material,
+            GameState::Editor,
+            Vec2::from_array(obstacle.position),
+            obstacle.radius,
+        );
+    }
+
+    commands.insert_resource(EditorAssets { obstacle_material });
+
+    commands.spawn((
+        StateScoped(GameState::Editor),
+        TextBundle::from_section(
+            format!(
+                "Level editor\nLeft click: place obstacle\nRight click: remove obstacle\n\
+                 [{SHRINK_BRUSH_KEY:?}]/[{GROW_BRUSH_KEY:?}]: obstacle size\n\
+                 WASD or arrows: pan\n[{SAVE_LAYOUT_KEY:?}]: save, [{LOAD_LAYOUT_KEY:?}]: reload\n\
+                 Escape: back to the menu"
+            ),
+            TextStyle {
+                font_size: 18.,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(20.),
+            top: Val::Px(20.),
+            ..default()
+        }),
+    ));
+
+    if let Ok(mut camera) = camera.get_single_mut() {
+        camera.translation.x = 0.;
+        camera.translation.y = 0.;
+    }
+}
+
+/// Move the camera around the arena with the movement keys.
+fn pan_editor_camera(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    mut camera: Query<&mut Transform, With<WorldCamera>>,
+    bounds: Res<WorldBounds>,
+    time: Res<Time>,
+) {
+    let Ok(mut camera) = camera.get_single_mut() else {
+        return;
+    };
+
+    let direction = [
+        ([KeyCode::KeyA, KeyCode::ArrowLeft], Vec2::NEG_X),
+        ([KeyCode::KeyD, KeyCode::ArrowRight], Vec2::X),
+        ([KeyCode::KeyW, KeyCode::ArrowUp], Vec2::Y),
+        ([KeyCode::KeyS, KeyCode::ArrowDown], Vec2::NEG_Y),
+    ]
+    .into_iter()
+    .filter(|(keys, _)| kb_input.any_pressed(*keys))
+    .map(|(_, direction)| direction)
+    .sum::<Vec2>()
+    .normalize_or_zero();
+
+    let position =
+        camera.translation.truncate() + direction * EDITOR_PAN_SPEED * time.delta_seconds();
+    let limit = bounds.half_extents();
+    camera.translation = position.clamp(-limit, limit).extend(camera.translation.z);
+}
+
+fn resize_brush(kb_input: Res<ButtonInput<KeyCode>>, mut brush: ResMut<EditorBrush>) {
+    let steps = match (
+        kb_input.just_pressed(SHRINK_BRUSH_KEY),
+        kb_input.just_pressed(GROW_BRUSH_KEY),
+    ) {
+        (true, false) => -1.,
+        (false, true) => 1.,
+        _ => return,
+    };
+
+    let (min, max) = BRUSH_RADIUS_RANGE;
+    brush.radius = (brush.radius + steps * BRUSH_RADIUS_STEP).clamp(min, max);
+}
+
+/// Place an obstacle on the grid point under the cursor on left click.
+///
+/// Spots where it wouldn't fully fit inside the arena, or would overlap an
+/// obstacle already there, are skipped.
+#[allow(clippy::too_many_arguments)]
+fn place_obstacle(
+    mut commands: Commands,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    cursor: Res<CursorWorld>,
+    brush: Res<EditorBrush>,
+    obstacles: Query<(&Transform, &Collidable), With<Obstacle>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    assets: Res<EditorAssets>,
+    bounds: Res<WorldBounds>,
+) {
+    if !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let position = snap_to_grid(cursor.0);
+
+    if bounds.clamp_world_position(position, brush.radius) != position {
+        return;
+    }
+
+    let overlaps = obstacles.iter().any(|(transform, collidable)| {
+        transform.translation.truncate().distance(position) < collidable.radius + brush.radius
+    });
+
+    if overlaps {
+        return;
+    }
+
+    spawn_obstacle(
+        &mut commands,
+        &mut meshes,
+        &assets.obstacle_material,
+        GameState::Editor,
+        position,
+        brush.radius,
+    );
+}
+
+/// Remove the obstacle under the cursor on right click.
+fn remove_obstacle(
+    mut commands: Commands,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    cursor: Res<CursorWorld>,
+    obstacles: Query<(Entity, &Transform, &Collidable), With<Obstacle>>,
+) {
+    if !mouse_input.just_pressed(MouseButton::Right) {
+        return;
+    }
+
+    let under_cursor = obstacles.iter().find(|(_, transform, collidable)| {
+        transform.translation.truncate().distance(cursor.0) <= collidable.radius
+    });
+
+    if let Some((entity, ..)) = under_cursor {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Write the obstacles in the editor to disk, and use them for the next run.
+fn save_layout(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    obstacles: Query<(&Transform, &Collidable), With<Obstacle>>,
+    mut layout: ResMut<ObstacleLayout>,
+) {
+    if !kb_input.just_pressed(SAVE_LAYOUT_KEY) {
+        return;
+    }
+
+    layout.obstacles = obstacles
+        .iter()
+        .map(|(transform, collidable)| ObstacleSpec {
+            position: transform.translation.truncate().to_array(),
+            radius: collidable.radius,
+        })
+        .collect();
+    layout.save(LAYOUT_PATH);
+}
+
+/// Replace the obstacles in the editor with the layout on disk.
+fn load_layout(
+    mut commands: Commands,
+    kb_input: Res<ButtonInput<KeyCode>>,
+    obstacles: Query<Entity, With<Obstacle>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    assets: Res<EditorAssets>,
+    mut layout: ResMut<ObstacleLayout>,
+) {
+    if !kb_input.just_pressed(LOAD_LAYOUT_KEY) {
+        return;
+    }
+
+    for entity in &obstacles {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    *layout = ObstacleLayout::load(LAYOUT_PATH);
+
+    for obstacle in &layout.obstacles {
+        spawn_obstacle(
+            &mut commands,
+            &mut meshes,
+            &assets.obstacle_material,
+            GameState::Editor,
+            Vec2::from_array(obstacle.position),
+            obstacle.radius,
+        );
+    }
+}
+
+/// Draw the snapping grid over the arena, and where the next obstacle would go.
+fn draw_editor_grid(
+    mut gizmos: Gizmos,
+    cursor: Res<CursorWorld>,
+    brush: Res<EditorBrush>,
+    bounds: Res<WorldBounds>,
+) {
+    let half = bounds.half_extents();
+    let line_color = Color::srgba(1.0, 1.0, 1.0, 0.08);
+
+    let mut x = (-half.x / GRID_SIZE).ceil() * GRID_SIZE;
+    while x <= half.x {
+        gizmos.line_2d(Vec2::new(x, -half.y), Vec2::new(x, half.y), line_color);
+        x += GRID_SIZE;
+    }
+
+    let mut y = (-half.y / GRID_SIZE).ceil() * GRID_SIZE;
+    while y <= half.y {
+        gizmos.line_2d(Vec2::new(-half.x, y), Vec2::new(half.x, y), line_color);
+        y += GRID_SIZE;
+    }
+
+    gizmos.circle_2d(
+        snap_to_grid(cursor.0),
+        brush.radius,
+        Color::srgb(0.3, 1.0, 0.5),
+    );
+}
+
+/// Go back to the main menu. Unsaved changes are dropped.
+fn leave_editor(kb_input: Res<ButtonInput<KeyCode>>, mut next_state: ResMut<NextState<GameState>>) {
+    if kb_input.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Menu);
+    }
+}
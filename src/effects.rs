@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+
+use crate::state::GameSet;
+
+/// What a timed effect does while it lasts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EffectKind {
+    /// Multiplies movement speed, above one for a boost and below for a slow.
+    SpeedMultiplier(f32),
+    /// Ignores all incoming damage.
+    Invincible,
+}
+
+#[derive(Debug)]
+pub struct TimedEffect {
+    pub kind: EffectKind,
+    pub timer: Timer,
+}
+
+/// Temporary effects currently active on an entity.
+///
+/// Stats like `MovementSpeed` are never changed by an effect. Systems ask for
+/// the combined effect instead, so once one expires its change is gone with
+/// it and nothing has to be undone.
+#[derive(Component, Default, Debug)]
+pub struct TimedEffects {
+    effects: Vec<TimedEffect>,
+}
+
+impl TimedEffects {
+    /// Start an effect for `seconds`. Adding one that is already running
+    /// refreshes its duration instead of stacking a second copy.
+    pub fn add(&mut self, kind: EffectKind, seconds: f32) {
+        let timer = Timer::from_seconds(seconds, TimerMode::Once);
+
+        match self.effects.iter_mut().find(|effect| effect.kind == kind) {
+            Some(effect) => effect.timer = timer,
+            None => self.effects.push(TimedEffect { kind, timer }),
+        }
+    }
+
+    /// Product of every active speed multiplier, one when there are none.
+    pub fn speed_multiplier(&self) -> f32 {
+        self.effects
+            .iter()
+            .map(|effect| match effect.kind {
+                EffectKind::SpeedMultiplier(multiplier) => multiplier,
+                _ => 1.,
+            })
+            .product()
+    }
+
+    pub fn is_invincible(&self) -> bool {
+        self.effects
+            .iter()
+            .any(|effect| effect.kind == EffectKind::Invincible)
+    }
+}
+
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, tick_effects.in_set(GameSet::Combat));
+    }
+}
+
+/// Advance every effect and drop the ones that have run out.
+fn tick_effects(mut entities: Query<&mut TimedEffects>, time: Res<Time>) {
+    for mut effects in &mut entities {
+        // Skip the change detection write for the common case of no effects
+        if effects.effects.is_empty() {
+            continue;
+        }
+
+        effects
+            .effects
+            .retain_mut(|effect| !effect.timer.tick(time.delta()).finished());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+
+    #[test]
+    fn speed_multiplier_wears_off() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(50)))
+            .add_systems(Update, tick_effects);
+
+        let mut effects = TimedEffects::default();
+        effects.add(EffectKind::SpeedMultiplier(1.5), 0.2);
+        let entity = app.world_mut().spawn(effects).id();
+
+        app.update();
+        let effects = app.world().get::<TimedEffects>(entity).unwrap();
+        assert_eq!(effects.speed_multiplier(), 1.5);
+
+        for _ in 0..5 {
+            app.update();
+        }
+        let effects = app.world().get::<TimedEffects>(entity).unwrap();
+        assert!(effects.effects.is_empty());
+        assert_eq!(effects.speed_multiplier(), 1.);
+    }
+}
@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::combat::OpponentHit;
+use crate::particles::Decorative;
+use crate::state::{GameSet, GameState};
+
+/// Seconds a damage number stays on screen.
+const DAMAGE_NUMBER_LIFETIME: f32 = 0.8;
+
+/// Speed damage numbers rise at, in world units per second.
+const DAMAGE_NUMBER_RISE: f32 = 60.;
+
+/// Largest sideways drift of a damage number, in world units per second, so
+/// numbers from quick hits in a row don't stack exactly on top of each other.
+const DAMAGE_NUMBER_DRIFT: f32 = 20.;
+
+/// Height above the center of the hit opponent damage numbers start at.
+const DAMAGE_NUMBER_OFFSET: f32 = 20.;
+
+const DAMAGE_NUMBER_FONT_SIZE: f32 = 18.;
+
+/// World space text drifting along and fading out, like a damage number.
+#[derive(Component)]
+pub struct FloatingText {
+    pub velocity: Vec2,
+    pub lifetime: Timer,
+}
+
+pub struct FloatingTextPlugin;
+
+impl Plugin for FloatingTextPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (spawn_damage_numbers, update_floating_text).in_set(GameSet::Cleanup),
+        );
+    }
+}
+
+/// Pop a number with the damage dealt above every opponent the player hit.
+///
+/// The numbers are decorative, so the `EffectBudget` keeps a big burst of
+/// hits from piling up text entities.
+fn spawn_damage_numbers(
+    mut commands: Commands,
+    mut hits: EventReader<OpponentHit>,
+    targets: Query<&Transform>,
+) {
+    let mut rng = rand::thread_rng();
+
+    for hit in hits.read() {
+        let Ok(transform) = targets.get(hit.target) else {
+            continue;
+        };
+
+        let position = transform.translation.truncate() + Vec2::Y * DAMAGE_NUMBER_OFFSET;
+        let drift = rng.gen_range(-DAMAGE_NUMBER_DRIFT..=DAMAGE_NUMBER_DRIFT);
+
+        commands.spawn((
+            FloatingText {
+                velocity: Vec2::new(drift, DAMAGE_NUMBER_RISE),
+                lifetime: Timer::from_seconds(DAMAGE_NUMBER_LIFETIME, TimerMode::Once),
+            },
+            Decorative,
+            StateScoped(GameState::Playing),
+            Text2dBundle {
+                text: Text::from_section(
+                    format!("{:.0}", hit.damage),
+                    TextStyle {
+                        font_size: DAMAGE_NUMBER_FONT_SIZE,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ),
+                // Above every sprite in the arena
+                transform: Transform::from_translation(position.extend(10.)),
+                ..default()
+            },
+        ));
+    }
+}
+
+/// Move floating texts along, fade them out and despawn them when their time
+/// is up.
+fn update_floating_text(
+    mut commands: Commands,
+    mut texts: Query<(Entity, &mut FloatingText, &mut Transform, &mut Text)>,
+    time: Res<Time>,
+) {
+    for (entity, mut floating, mut transform, mut text) in &mut texts {
+        if floating.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        transform.translation += (floating.velocity * time.delta_seconds()).extend(0.);
+
+        let alpha = floating.lifetime.fraction_remaining();
+        for section in &mut text.sections {
+            section.style.color.set_alpha(alpha);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use bevy::prelude::*;
+
+use crate::animation::{walk_cycle_layout, AnimationIndices, AnimationTimer};
+use crate::combat::resolve_collisions;
+use crate::components::{SpriteFallback, Velocity};
+use crate::config::GameConfig;
+use crate::player::{Player, PLAYER_SPRITE};
+use crate::state::{GameSet, GameState};
+
+/// Most fixed steps recorded in a run, five minutes at the physics rate.
+/// Recording stops once it is full, so the ghost only covers the start of a
+/// longer run.
+const MAX_RECORDED_STEPS: usize = 60 * 60 * 5;
+
+/// Opacity of the ghost replaying the previous run.
+const GHOST_ALPHA: f32 = 0.35;
+
+/// Player positions of the current run, one per fixed step.
+#[derive(Resource, Default)]
+pub struct RecordRun {
+    positions: Vec<Vec2>,
+}
+
+/// Translucent copy of the player walking the path of the previous run.
+#[derive(Component)]
+pub struct PlaybackGhost {
+    path: Vec<Vec2>,
+    step: usize,
+}
+
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RecordRun>()
+            .add_systems(OnEnter(GameState::Playing), start_recording)
+            .add_systems(
+                FixedUpdate,
+                (
+                    play_ghost.in_set(GameSet::Movement),
+                    record_run.after(resolve_collisions).in_set(GameSet::Physics),
+                ),
+            );
+    }
+}
+
+/// Turn the last run's recording into a ghost and start recording afresh.
+///
+/// The recording survives leaving the playing state, so a retry races the
+/// run that just ended. The first run of a session has nothing to replay.
+fn start_recording(
+    mut commands: Commands,
+    mut record: ResMut<RecordRun>,
+    asset_server: Res<AssetServer>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    config: Res<GameConfig>,
+) {
+    let path = std::mem::take(&mut record.positions);
+
+    let Some(&start) = path.first() else {
+        return;
+    };
+
+    commands.spawn((
+        PlaybackGhost { path, step: 0 },
+        StateScoped(GameState::Playing),
+        Velocity::default(),
+        SpriteFallback(Color::srgba(0.0, 1.0, 0.0, GHOST_ALPHA)),
+        SpriteBundle {
+            texture: asset_server.load(PLAYER_SPRITE),
+            sprite: Sprite {
+                color: Color::srgba(1.0, 1.0, 1.0, GHOST_ALPHA),
+                custom_size: Some(Vec2::splat(config.collision_radius * 2.)),
+                ..default()
+            },
+            transform: Transform {
+                // Just behind the player, so it never covers the real one
+                translation: start.extend(1.9),
+                ..default()
+            },
+            ..default()
+        },
+        TextureAtlas {
+            layout: layouts.add(walk_cycle_layout()),
+            index: 0,
+        },
+        AnimationIndices::walk_cycle(),
+        AnimationTimer::default(),
+    ));
+}
+
+/// Store where the player ended up this step, once collisions have settled.
+fn record_run(mut record: ResMut<RecordRun>, player: Query<&Transform, With<Player>>) {
+    let Ok(transform) = player.get_single() else {
+        return;
+    };
+
+    if record.positions.len() < MAX_RECORDED_STEPS {
+        record.positions.push(transform.translation.truncate());
+    }
+}
+
+/// Move the ghost one recorded step along its path, removing it once the
+/// path runs out.
+///
+/// Its velocity is worked out from the step, so it animates and turns around
+/// like the player did.
+fn play_ghost(
+    mut commands: Commands,
+    mut ghosts: Query<(Entity, &mut PlaybackGhost, &mut Transform, &mut Velocity, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut ghost, mut transform, mut velocity, mut sprite) in &mut ghosts {
+        ghost.step += 1;
+
+        let Some(&position) = ghost.path.get(ghost.step) else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+
+        let step = position - transform.translation.truncate();
+        velocity.0 = step / time.delta_seconds();
+
+        if step.x != 0. {
+            sprite.flip_x = step.x < 0.;
+        }
+
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_inspector_egui::bevy_egui::{egui, EguiContext, EguiContexts, EguiPlugin};
+use bevy_inspector_egui::bevy_inspector::ui_for_entity;
+use bevy_inspector_egui::DefaultInspectorConfigPlugin;
+
+use crate::camera::CursorWorld;
+use crate::components::{Collidable, Health, MovementSpeed};
+use crate::state::GameSet;
+
+/// Key that shows and hides the inspector.
+const INSPECTOR_TOGGLE_KEY: KeyCode = KeyCode::F4;
+
+/// Development tool for looking at and tuning an entity's components while
+/// the game runs.
+///
+/// Clicking a collidable selects it, and its components show up in an egui
+/// window where they can be edited. While the inspector is open it takes
+/// every left click, so selecting never fires a projectile.
+#[derive(Resource, Default)]
+struct Inspector {
+    enabled: bool,
+    selected: Option<Entity>,
+}
+
+/// Only compiled in with the `inspector` feature, so release builds don't
+/// carry it or egui.
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((EguiPlugin, DefaultInspectorConfigPlugin))
+            // The game's own stats, everything from Bevy is registered already
+            .register_type::<Health>()
+            .register_type::<MovementSpeed>()
+            .register_type::<Collidable>()
+            .init_resource::<Inspector>()
+            // Available in every state, including while paused. Before combat,
+            // so a click it takes never reaches the player's attacks
+            .add_systems(
+                Update,
+                (
+                    toggle_inspector,
+                    (select_entity, inspector_window, draw_selection)
+                        .chain()
+                        .run_if(inspector_enabled),
+                )
+                    .chain()
+                    .before(GameSet::Combat),
+            );
+    }
+}
+
+fn inspector_enabled(inspector: Res<Inspector>) -> bool {
+    inspector.enabled
+}
+
+fn toggle_inspector(kb_input: Res<ButtonInput<KeyCode>>, mut inspector: ResMut<Inspector>) {
+    if kb_input.just_pressed(INSPECTOR_TOGGLE_KEY) {
+        inspector.enabled = !inspector.enabled;
+    }
+}
+
+/// Select the collidable under the cursor on click, or clear the selection
+/// when clicking empty space.
+///
+/// The click is consumed, so it stays released for everything else until the
+/// button is pressed again. Clicks on the inspector window itself are left to
+/// egui and keep the selection.
+fn select_entity(
+    mut mouse_input: ResMut<ButtonInput<MouseButton>>,
+    mut contexts: EguiContexts,
+    cursor: Res<CursorWorld>,
+    collidables: Query<(Entity, &Transform, &Collidable)>,
+    mut inspector: ResMut<Inspector>,
+) {
+    if !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+    mouse_input.reset(MouseButton::Left);
+
+    if contexts
+        .try_ctx_mut()
+        .is_some_and(|ctx| ctx.is_pointer_over_area())
+    {
+        return;
+    }
+
+    // The closest center wins where bodies overlap
+    inspector.selected = collidables
+        .iter()
+        .map(|(entity, transform, collidable)| {
+            let distance = transform.translation.truncate().distance(cursor.0);
+            (entity, distance, collidable.radius)
+        })
+        .filter(|&(_, distance, radius)| distance <= radius)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(entity, ..)| entity);
+}
+
+/// Show every component of the selected entity in an egui window, editable
+/// in place.
+fn inspector_window(world: &mut World) {
+    let Ok(context) = world
+        .query_filtered::<&mut EguiContext, With<PrimaryWindow>>()
+        .get_single(world)
+    else {
+        return;
+    };
+    let mut context = context.clone();
+
+    // Selected entities can die or despawn at any point
+    let selected = world
+        .resource::<Inspector>()
+        .selected
+        .filter(|&entity| world.get_entity(entity).is_some());
+
+    egui::Window::new("Inspector").show(context.get_mut(), |ui| {
+        egui::ScrollArea::vertical().show(ui, |ui| match selected {
+            Some(entity) => ui_for_entity(world, entity, ui),
+            None => {
+                ui.label("Click an entity to select it");
+            }
+        });
+    });
+}
+
+/// Ring the selected entity so it is clear what is being edited.
+fn draw_selection(
+    mut gizmos: Gizmos,
+    inspector: Res<Inspector>,
+    entities: Query<(&Transform, &Collidable)>,
+) {
+    let Some(Ok((transform, collidable))) = inspector.selected.map(|entity| entities.get(entity))
+    else {
+        return;
+    };
+
+    gizmos.circle_2d(
+        transform.translation.truncate(),
+        collidable.radius + 4.,
+        Color::srgb(0.2, 1.0, 1.0),
+    );
+}
@@ -0,0 +1,834 @@
+use bevy::math::vec2;
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::animation::{walk_cycle_layout, AnimationIndices, AnimationTimer};
+use crate::boss::BossFight;
+use crate::combat::{
+    AttackCooldown, AttackDamage, Dying, Knockback, Projectile, Score, Stunned, WindUp,
+    ENEMY_ATTACK_COOLDOWN,
+};
+use crate::components::{
+    Collidable, CollisionLayers, Health, MovementSpeed, SpriteFallback, Velocity,
+    COLLISION_RADIUS,
+};
+use crate::config::GameConfig;
+use crate::difficulty::Difficulty;
+use crate::effects::TimedEffects;
+use crate::player::Player;
+use crate::rng::GameRng;
+use crate::state::{GameClock, GameSet, GameState, GameTimer};
+use crate::world::{line_of_sight, Obstacle, WorldBounds};
+
+/// Default movement speed of the opponent, slower than the player so they can
+/// escape. See `GameConfig`.
+pub const OPPONENT_SPEED: f32 = 120.;
+
+/// Walk cycle sprite sheet used for opponents, relative to the assets folder.
+const OPPONENT_SPRITE: &str = "sprites/opponent.png";
+
+/// Distance between opponent centers below which they steer away from each other.
+const SEPARATION_RADIUS: f32 = COLLISION_RADIUS * 3.;
+
+/// How strongly separation weighs against chasing the player.
+const SEPARATION_WEIGHT: f32 = 1.5;
+
+/// How far ahead of a projectile an opponent has to be to try and sidestep it,
+/// in world units.
+const DODGE_LOOKAHEAD: f32 = 250.;
+
+/// Chance an opponent reacts to a given projectile at all.
+const DODGE_CHANCE: f64 = 0.5;
+
+/// How strongly a dodge weighs against everything else the opponent wants,
+/// at its strongest just before the projectile arrives.
+const DODGE_WEIGHT: f32 = 2.;
+
+/// Furthest ahead an opponent predicts the player's movement, in seconds.
+const MAX_LEAD_TIME: f32 = 0.6;
+
+/// Longest lead an opponent takes, as a fraction of its distance to the
+/// player, so a sudden turn doesn't send it charging off the wrong way.
+const MAX_LEAD_FRACTION: f32 = 0.5;
+
+/// How far ahead an opponent looks for obstacles in its way, in world units.
+const AVOID_LOOKAHEAD: f32 = 40.;
+
+/// Angles tried, one after the other, to get around an obstacle blocking the
+/// way to the player, in radians.
+const AVOID_ANGLES: [f32; 4] = [
+    std::f32::consts::FRAC_PI_6,
+    std::f32::consts::FRAC_PI_3,
+    std::f32::consts::FRAC_PI_2,
+    std::f32::consts::FRAC_PI_2 + std::f32::consts::FRAC_PI_4,
+];
+
+/// Distance at which an opponent has reached a spot it walks to, like a patrol
+/// waypoint or where it last saw the player.
+const ARRIVAL_DISTANCE: f32 = 8.;
+
+/// Furthest a patrolling opponent notices the player from, in world units.
+const PATROL_DETECTION_RANGE: f32 = 350.;
+
+/// Fraction of its full speed an opponent walks its patrol at.
+const PATROL_PACE: f32 = 0.5;
+
+/// Distance between the corners of the patrol routes given to the first
+/// opponents of a run.
+const PATROL_ROUTE_SIZE: f32 = 120.;
+
+/// Seconds between waves of opponents.
+const WAVE_INTERVAL: f32 = 10.;
+
+/// Opponents in the first wave.
+const WAVE_BASE_SIZE: u32 = 2;
+
+/// Extra opponents added to every wave after the first.
+const WAVE_SIZE_GROWTH: u32 = 1;
+
+/// Waves in a run, clearing the last one wins.
+const WAVE_COUNT: u32 = 5;
+
+/// Seconds a spawn portal warns for before its opponent comes through.
+const PORTAL_DELAY: f32 = 1.2;
+
+/// Color of the warning circles drawn over a spawn portal.
+const PORTAL_COLOR: Color = Color::srgb(0.8, 0.3, 1.0);
+
+#[derive(Component)]
+pub struct Opponent;
+
+/// What an opponent knows about where the player is.
+///
+/// Obstacles block the view, see `line_of_sight`. While the player is hidden
+/// the opponent heads for where it saw them last, then goes back to its
+/// `Patrol`, or stays put without one, once it gets there without spotting
+/// them again.
+#[derive(Component, Default)]
+pub struct Awareness {
+    pub last_seen: Option<Vec2>,
+}
+
+/// Projectiles an opponent has noticed coming at it, and whether it decided to
+/// dodge each of them.
+///
+/// The decision is made once per projectile, so an opponent that chose not to
+/// react doesn't start dodging a frame later.
+#[derive(Component, Default)]
+pub struct Reflexes {
+    noticed: Vec<(Entity, bool)>,
+}
+
+/// Route an opponent walks in a loop while it isn't chasing the player.
+///
+/// Patrolling opponents only notice the player within
+/// `PATROL_DETECTION_RANGE`, while any other opponent notices them across the
+/// arena. Add it to an opponent after `spawn_opponent` to give it a route.
+#[derive(Component, Clone, Debug)]
+pub struct Patrol {
+    pub waypoints: Vec<Vec2>,
+    /// The waypoint currently walked to.
+    pub index: usize,
+}
+
+impl Patrol {
+    pub fn new(waypoints: Vec<Vec2>) -> Self {
+        Self {
+            waypoints,
+            index: 0,
+        }
+    }
+
+    /// Square loop with `corner` as its first waypoint.
+    fn square(corner: Vec2, size: f32) -> Self {
+        Self::new(vec![
+            corner,
+            corner + vec2(size, 0.),
+            corner + vec2(size, size),
+            corner + vec2(0., size),
+        ])
+    }
+
+    fn waypoint(&self) -> Option<Vec2> {
+        self.waypoints.get(self.index).copied()
+    }
+
+    /// Move on to the next waypoint, starting over after the last.
+    fn advance(&mut self) {
+        if !self.waypoints.is_empty() {
+            self.index = (self.index + 1) % self.waypoints.len();
+        }
+    }
+}
+
+/// Kind of opponent, deciding its stats and look.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EnemyType {
+    /// The all rounder every other type is measured against.
+    #[default]
+    Grunt,
+    /// Slow and tanky, hits hard.
+    Brute,
+    /// Fast and fragile, hits lightly.
+    Runner,
+}
+
+/// Stats of an enemy type, as multiples of the opponent values in
+/// `GameConfig` so the config still tunes every type at once.
+struct EnemyStats {
+    health: f32,
+    speed: f32,
+    radius: f32,
+    damage: f32,
+    /// Tint applied over the shared sprite sheet.
+    tint: Color,
+    /// Color used instead when the sprite is missing.
+    fallback: Color,
+}
+
+impl EnemyType {
+    fn stats(self) -> EnemyStats {
+        match self {
+            Self::Grunt => EnemyStats {
+                health: 1.,
+                speed: 1.,
+                radius: 1.,
+                damage: 1.,
+                tint: Color::WHITE,
+                fallback: Color::srgb(1.0, 0.0, 0.0),
+            },
+            Self::Brute => EnemyStats {
+                health: 2.5,
+                speed: 0.6,
+                radius: 1.4,
+                damage: 1.6,
+                tint: Color::srgb(0.7, 0.5, 1.0),
+                fallback: Color::srgb(0.5, 0.1, 0.6),
+            },
+            Self::Runner => EnemyStats {
+                health: 0.5,
+                speed: 1.6,
+                radius: 0.75,
+                damage: 0.6,
+                tint: Color::srgb(1.0, 0.9, 0.4),
+                fallback: Color::srgb(1.0, 0.6, 0.0),
+            },
+        }
+    }
+
+    /// Pick a type for a wave, mostly grunts with some of each of the others.
+    ///
+    /// Every difficulty `tier` turns a tenth of the grunts into the other two
+    /// types, until only a fifth are left.
+    fn random(rng: &mut impl Rng, tier: u32) -> Self {
+        let grunts = 6u32.saturating_sub(tier).max(2);
+        let brutes = grunts + (10 - grunts) / 2;
+
+        match rng.gen_range(0..10) {
+            roll if roll < grunts => Self::Grunt,
+            roll if roll < brutes => Self::Brute,
+            _ => Self::Runner,
+        }
+    }
+
+    pub fn max_health(self, config: &GameConfig) -> f32 {
+        config.max_health * self.stats().health
+    }
+}
+
+/// Where an opponent from a wave is about to appear, giving the player a
+/// moment to get out of the way.
+///
+/// It can't be destroyed, once the timer runs out `spawn_portals` always
+/// replaces it with an opponent of type `kind`.
+#[derive(Component)]
+pub struct SpawnPortal {
+    pub kind: EnemyType,
+    pub timer: Timer,
+}
+
+/// Sprite sheet and atlas layout shared by every opponent.
+#[derive(Resource)]
+pub struct OpponentSprites {
+    texture: Handle<Image>,
+    layout: Handle<TextureAtlasLayout>,
+}
+
+/// Sends ever larger waves of opponents in from the edges of the arena.
+#[derive(Resource)]
+pub struct WaveSpawner {
+    pub timer: GameTimer,
+    /// Number of waves spawned so far this run.
+    pub wave: u32,
+    pub base_size: u32,
+    pub size_growth: u32,
+    /// Number of waves in a run.
+    pub total: u32,
+}
+
+impl Default for WaveSpawner {
+    fn default() -> Self {
+        Self {
+            timer: GameTimer::from_seconds(WAVE_INTERVAL, TimerMode::Repeating),
+            wave: 0,
+            base_size: WAVE_BASE_SIZE,
+            size_growth: WAVE_SIZE_GROWTH,
+            total: WAVE_COUNT,
+        }
+    }
+}
+
+impl WaveSpawner {
+    /// Number of opponents in the next wave.
+    pub fn next_wave_size(&self) -> u32 {
+        self.base_size + self.wave * self.size_growth
+    }
+
+    /// Whether every wave of the run has been sent.
+    pub fn is_finished(&self) -> bool {
+        self.wave >= self.total
+    }
+}
+
+pub struct OpponentPlugin;
+
+impl Plugin for OpponentPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WaveSpawner>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                ((setup_opponent_sprites, spawn_opponents).chain(), reset_wave_spawner),
+            )
+            .add_systems(
+                FixedUpdate,
+                (spawn_waves, spawn_portals, move_opponent)
+                    .chain()
+                    .in_set(GameSet::Ai),
+            )
+            .add_systems(
+                Update,
+                (
+                    check_victory.in_set(GameSet::Cleanup),
+                    draw_spawn_portals.in_set(GameSet::Ui),
+                ),
+            );
+    }
+}
+
+fn setup_opponent_sprites(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    commands.insert_resource(OpponentSprites {
+        texture: asset_server.load(OPPONENT_SPRITE),
+        layout: layouts.add(walk_cycle_layout()),
+    });
+}
+
+fn spawn_opponents(
+    mut commands: Commands,
+    sprites: Res<OpponentSprites>,
+    bounds: Res<WorldBounds>,
+    config: Res<GameConfig>,
+) {
+    for (kind, position) in [
+        (EnemyType::Grunt, vec2(150., 0.)),
+        (EnemyType::Runner, vec2(-200., 150.)),
+        (EnemyType::Brute, vec2(100., -250.)),
+    ] {
+        let entity = spawn_opponent(&mut commands, &config, &sprites, &bounds, kind, position);
+        commands
+            .entity(entity)
+            .insert(Patrol::square(position, PATROL_ROUTE_SIZE));
+    }
+}
+
+/// Spawn an opponent of type `kind` as close to `position` as fits inside the
+/// arena.
+pub fn spawn_opponent(
+    commands: &mut Commands,
+    config: &GameConfig,
+    sprites: &OpponentSprites,
+    bounds: &WorldBounds,
+    kind: EnemyType,
+    position: Vec2,
+) -> Entity {
+    let stats = kind.stats();
+    let radius = config.collision_radius * stats.radius;
+    let position = bounds.clamp_world_position(position, radius);
+
+    commands
+        .spawn((
+            (Opponent, kind, Awareness::default(), Reflexes::default()),
+            StateScoped(GameState::Playing),
+            Collidable { radius },
+            CollisionLayers::ENEMY,
+            Health::new(kind.max_health(config)),
+            MovementSpeed(config.opponent_speed * stats.speed),
+            TimedEffects::default(),
+            Velocity::default(),
+            AttackCooldown::new(ENEMY_ATTACK_COOLDOWN),
+            AttackDamage(config.enemy_attack_damage * stats.damage),
+            SpriteFallback(stats.fallback),
+            SpriteBundle {
+                texture: sprites.texture.clone(),
+                sprite: Sprite {
+                    color: stats.tint,
+                    // Sized to the collision circle so visuals and physics line up
+                    custom_size: Some(Vec2::splat(radius * 2.)),
+                    ..default()
+                },
+                transform: Transform {
+                    translation: position.extend(1.),
+                    ..default()
+                },
+                ..default()
+            },
+            TextureAtlas {
+                layout: sprites.layout.clone(),
+                index: 0,
+            },
+            AnimationIndices::walk_cycle(),
+            AnimationTimer::default(),
+        ))
+        .id()
+}
+
+/// Start every run back at the first wave.
+fn reset_wave_spawner(mut spawner: ResMut<WaveSpawner>) {
+    spawner.timer.reset();
+    spawner.wave = 0;
+}
+
+/// Open a portal for every opponent of the next wave at random points along
+/// the arena edge once the timer runs out.
+///
+/// The timer is a `GameTimer`, so it doesn't advance while paused or outside
+/// of a run. Waves come quicker and with tougher types as the
+/// `Difficulty` goes up.
+#[allow(clippy::too_many_arguments)]
+fn spawn_waves(
+    mut commands: Commands,
+    mut spawner: ResMut<WaveSpawner>,
+    bounds: Res<WorldBounds>,
+    config: Res<GameConfig>,
+    difficulty: Res<Difficulty>,
+    mut rng: ResMut<GameRng>,
+    clock: GameClock,
+) {
+    if spawner.is_finished() {
+        return;
+    }
+
+    spawner
+        .timer
+        .tick_scaled(&clock, difficulty.spawn_rate_multiplier(&config));
+
+    if !spawner.timer.just_finished() {
+        return;
+    }
+
+    let half = bounds.half_extents();
+    let rng = &mut rng.0;
+    let size = spawner.next_wave_size();
+
+    for _ in 0..size {
+        let along = rng.gen_range(-1.0..=1.0);
+        let position = match rng.gen_range(0..4) {
+            0 => vec2(along * half.x, half.y),
+            1 => vec2(along * half.x, -half.y),
+            2 => vec2(half.x, along * half.y),
+            _ => vec2(-half.x, along * half.y),
+        };
+
+        // Pulled in from the edge so the body fits, the same as
+        // spawn_opponent does, so the warning shows where it will appear
+        let kind = EnemyType::random(rng, difficulty.tier);
+        let radius = config.collision_radius * kind.stats().radius;
+        let position = bounds.clamp_world_position(position, radius);
+
+        commands.spawn((
+            SpawnPortal {
+                kind,
+                timer: Timer::from_seconds(PORTAL_DELAY, TimerMode::Once),
+            },
+            StateScoped(GameState::Playing),
+            TransformBundle::from_transform(Transform::from_translation(position.extend(0.))),
+        ));
+    }
+
+    spawner.wave += 1;
+    info!("Wave {} incoming with {} opponents", spawner.wave, size);
+}
+
+/// Bring an opponent through every spawn portal whose warning is over, as
+/// tough and fast as the current `Difficulty` makes it.
+#[allow(clippy::too_many_arguments)]
+fn spawn_portals(
+    mut commands: Commands,
+    mut portals: Query<(Entity, &mut SpawnPortal, &Transform)>,
+    sprites: Res<OpponentSprites>,
+    bounds: Res<WorldBounds>,
+    config: Res<GameConfig>,
+    difficulty: Res<Difficulty>,
+    time: Res<Time>,
+) {
+    for (entity, mut portal, transform) in &mut portals {
+        if !portal.timer.tick(time.delta()).finished() {
+            continue;
+        }
+
+        let kind = portal.kind;
+        let position = transform.translation.truncate();
+        let opponent = spawn_opponent(&mut commands, &config, &sprites, &bounds, kind, position);
+        commands.entity(opponent).insert((
+            Health::new(kind.max_health(&config) * difficulty.health_multiplier(&config)),
+            MovementSpeed(
+                config.opponent_speed * kind.stats().speed * difficulty.speed_multiplier(&config),
+            ),
+        ));
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Warn about each spawn portal with a circle that grows to the size of the
+/// opponent coming through, inside a faint outline of its final size.
+fn draw_spawn_portals(
+    mut gizmos: Gizmos,
+    portals: Query<(&SpawnPortal, &Transform)>,
+    config: Res<GameConfig>,
+) {
+    for (portal, transform) in &portals {
+        let position = transform.translation.truncate();
+        let radius = config.collision_radius * portal.kind.stats().radius;
+        let fraction = portal.timer.fraction();
+
+        gizmos.circle_2d(position, radius, PORTAL_COLOR.with_alpha(0.3));
+        gizmos.circle_2d(position, radius * fraction, PORTAL_COLOR);
+    }
+}
+
+/// Win the run once every wave has been sent, the boss has shown up, and no
+/// opponent is left, not even one still playing its death animation or
+/// waiting in a portal.
+///
+/// Requiring a kill keeps an empty first frame, before anything has spawned,
+/// from counting as a win.
+pub fn check_victory(
+    opponents: Query<(), With<Opponent>>,
+    portals: Query<(), With<SpawnPortal>>,
+    spawner: Res<WaveSpawner>,
+    fight: Res<BossFight>,
+    score: Res<Score>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let cleared = opponents.is_empty() && portals.is_empty();
+
+    if cleared && spawner.is_finished() && fight.spawned && score.0 > 0 {
+        next_state.set(GameState::Victory);
+    }
+}
+
+/// Move every opponent towards the player, stopping once they touch, while
+/// steering away from other opponents that get too close.
+///
+/// Opponents that are being knocked back don't chase, otherwise they would
+/// walk straight back in and cancel the shove. Opponents winding up an attack
+/// hold still, and so do stunned ones. All of them still count as neighbors
+/// for everyone else's separation, while dying opponents are ignored entirely.
+///
+/// An obstacle in the way makes the opponent steer around it instead of
+/// pinning itself against it, see `steer_around`. Opponents only chase a
+/// player they can see, see `Awareness`, and otherwise walk their `Patrol`.
+/// They try to sidestep the player's projectiles, though not every time, see
+/// `Reflexes`.
+#[allow(clippy::type_complexity)]
+fn move_opponent(
+    // The steering opponents, and every opponent they keep their distance
+    // from. Both read transforms, so they can't be borrowed at once
+    mut opponents: ParamSet<(
+        Query<
+            (
+                Entity,
+                &mut Transform,
+                &mut Velocity,
+                &mut Awareness,
+                &mut Reflexes,
+                Option<&mut Patrol>,
+                &Collidable,
+                &MovementSpeed,
+                &TimedEffects,
+            ),
+            (
+                With<Opponent>,
+                Without<Knockback>,
+                Without<WindUp>,
+                Without<Stunned>,
+                Without<Dying>,
+            ),
+        >,
+        Query<(Entity, &Transform), (With<Opponent>, Without<Dying>)>,
+    )>,
+    player: Query<(&Transform, &Collidable, &Velocity), (With<Player>, Without<Opponent>)>,
+    obstacles: Query<(&Transform, &Collidable), (With<Obstacle>, Without<Opponent>)>,
+    projectiles: Query<
+        (Entity, &Transform, &Collidable, &CollisionLayers, &Projectile),
+        Without<Opponent>,
+    >,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
+) {
+    let Ok((player_transform, player_collidable, player_velocity)) = player.get_single() else {
+        return;
+    };
+
+    // Only the ones that can hit an opponent are worth dodging
+    let threats: Vec<(Entity, Vec2, Vec2, f32)> = projectiles
+        .iter()
+        .filter(|(_, _, _, layers, _)| layers.interacts(&CollisionLayers::ENEMY))
+        .filter_map(|(entity, transform, collidable, _, projectile)| {
+            let direction = projectile.velocity.try_normalize()?;
+            Some((entity, transform.translation.truncate(), direction, collidable.radius))
+        })
+        .collect();
+
+    // Snapshot positions first so every opponent steers off the same frame,
+    // otherwise the result would depend on iteration order
+    let positions: Vec<(Entity, Vec2)> = opponents
+        .p1()
+        .iter()
+        .map(|(entity, transform)| (entity, transform.translation.truncate()))
+        .collect();
+
+    let player_position = player_transform.translation.truncate();
+
+    for (
+        entity,
+        mut opponent_transform,
+        mut velocity,
+        mut awareness,
+        mut reflexes,
+        mut patrol,
+        opponent_collidable,
+        speed,
+        effects,
+    ) in &mut opponents.p0()
+    {
+        let position = opponent_transform.translation.truncate();
+        let speed = speed.0 * effects.speed_multiplier();
+        let in_range = patrol.is_none()
+            || position.distance(player_position) <= PATROL_DETECTION_RANGE;
+        let sees_player = in_range
+            && line_of_sight(
+                position,
+                player_position,
+                obstacles
+                    .iter()
+                    .map(|(transform, collidable)| (transform.translation.truncate(), collidable)),
+            );
+
+        // Walk right up to a player in sight, onto the spot they were seen, or
+        // along the patrol when there is nothing to go after
+        let (target, min_distance, pace) = if sees_player {
+            awareness.last_seen = Some(player_position);
+            (
+                Some(player_position),
+                player_collidable.radius + opponent_collidable.radius,
+                1.,
+            )
+        } else if awareness.last_seen.is_some() {
+            (awareness.last_seen, 0., 1.)
+        } else {
+            (patrol.as_deref().and_then(Patrol::waypoint), 0., PATROL_PACE)
+        };
+
+        // Head for where the player is going rather than where they are, but
+        // still stop once actually touching them
+        let aim = if sees_player {
+            lead_target(position, speed, player_position, player_velocity.0)
+        } else {
+            target.unwrap_or(position)
+        };
+
+        let (chase, gap) = match target {
+            Some(target) => {
+                let gap = ((target - position).length() - min_distance).max(0.);
+                let chase = if gap > 0. {
+                    (aim - position).normalize_or_zero()
+                } else {
+                    Vec2::ZERO
+                };
+                (chase, gap)
+            }
+            None => (Vec2::ZERO, 0.),
+        };
+
+        if !sees_player && gap < ARRIVAL_DISTANCE {
+            // Giving up on the player picks the patrol back up where it was
+            if awareness.last_seen.take().is_none() {
+                if let Some(patrol) = patrol.as_mut() {
+                    patrol.advance();
+                }
+            }
+        }
+
+        let heading_around = steer_around(position, chase, opponent_collidable, &obstacles);
+
+        // The push fades out towards the edge of the radius, so neighbors
+        // settle at a distance instead of flicking in and out of range
+        let separation: Vec2 = positions
+            .iter()
+            .filter(|(other, _)| *other != entity)
+            .filter_map(|(_, other_position)| {
+                let away = position - *other_position;
+                let overlap = 1. - away.length() / SEPARATION_RADIUS;
+                (overlap > 0.).then(|| away.normalize_or_zero() * overlap)
+            })
+            .sum();
+
+        let dodge = dodge_projectiles(
+            position,
+            opponent_collidable,
+            &threats,
+            &mut reflexes,
+            &mut rng.0,
+        );
+
+        let heading = (heading_around + separation * SEPARATION_WEIGHT + dodge * DODGE_WEIGHT)
+            .clamp_length_max(1.);
+        let mut move_delta = heading * speed * pace * time.delta_seconds();
+
+        // Only close the gap up to the point where the circles touch
+        let forward = move_delta.dot(chase);
+        if forward > gap {
+            move_delta -= chase * (forward - gap);
+        }
+
+        opponent_transform.translation += move_delta.extend(0.);
+        // Not integrated anywhere, only kept so others can tell how it moves
+        velocity.0 = move_delta / time.delta_seconds();
+    }
+}
+
+/// Point an opponent at `position` moving at `speed` should head for to cut
+/// off a player at `player_position` moving along `player_velocity`.
+///
+/// The lead is how far the player gets in the time it would take to reach
+/// them, limited by `MAX_LEAD_TIME` and `MAX_LEAD_FRACTION` so a player that
+/// turns around doesn't leave opponents overshooting far behind them.
+fn lead_target(position: Vec2, speed: f32, player_position: Vec2, player_velocity: Vec2) -> Vec2 {
+    let distance = position.distance(player_position);
+
+    if speed <= 0. {
+        return player_position;
+    }
+
+    let time_to_reach = (distance / speed).min(MAX_LEAD_TIME);
+    let lead = (player_velocity * time_to_reach).clamp_length_max(distance * MAX_LEAD_FRACTION);
+    player_position + lead
+}
+
+/// Direction to sidestep the projectiles about to hit an opponent at
+/// `position`, zero when there are none or it chose not to react.
+///
+/// `threats` holds each projectile's entity, position, unit direction and
+/// radius. The push is sideways to the projectile, on whichever side the
+/// opponent already is, and grows as the projectile gets closer.
+fn dodge_projectiles(
+    position: Vec2,
+    collidable: &Collidable,
+    threats: &[(Entity, Vec2, Vec2, f32)],
+    reflexes: &mut Reflexes,
+    rng: &mut impl Rng,
+) -> Vec2 {
+    let incoming: Vec<(Entity, Vec2, f32)> = threats
+        .iter()
+        .filter_map(|&(entity, projectile_position, direction, radius)| {
+            let offset = position - projectile_position;
+            let ahead = offset.dot(direction);
+            let miss = direction.perp_dot(offset);
+
+            if ahead <= 0. || ahead > DODGE_LOOKAHEAD || miss.abs() > collidable.radius + radius {
+                return None;
+            }
+
+            // Left of the projectile keeps going left, a dead center hit too
+            let side = if miss >= 0. { 1. } else { -1. };
+            let urgency = 1. - ahead / DODGE_LOOKAHEAD;
+            Some((entity, direction.perp() * side, urgency))
+        })
+        .collect();
+
+    // Forget projectiles that passed, hit something or were never a threat
+    reflexes
+        .noticed
+        .retain(|(entity, _)| incoming.iter().any(|(other, ..)| other == entity));
+
+    incoming
+        .into_iter()
+        .filter(|&(entity, ..)| {
+            match reflexes.noticed.iter().find(|(noticed, _)| *noticed == entity) {
+                Some(&(_, dodges)) => dodges,
+                None => {
+                    let dodges = rng.gen_bool(DODGE_CHANCE);
+                    reflexes.noticed.push((entity, dodges));
+                    dodges
+                }
+            }
+        })
+        .map(|(_, away, urgency)| away * urgency)
+        .sum::<Vec2>()
+        .clamp_length_max(1.)
+}
+
+/// Turn `direction` away from any obstacle blocking it, or leave it as is if
+/// the way is clear.
+///
+/// Rotated directions are tried on the side facing away from the obstacle
+/// first, so the opponent keeps going around the same way instead of
+/// flicking between sides. If every direction is blocked, the original one is
+/// kept and collisions slide the opponent along the obstacle.
+#[allow(clippy::type_complexity)]
+fn steer_around(
+    position: Vec2,
+    direction: Vec2,
+    collidable: &Collidable,
+    obstacles: &Query<(&Transform, &Collidable), (With<Obstacle>, Without<Opponent>)>,
+) -> Vec2 {
+    // The obstacle in the way, if any. Touching counts as blocked too, since
+    // a sweep from inside the contact never finds it
+    let blocker = |direction: Vec2| {
+        obstacles.iter().find_map(|(transform, obstacle)| {
+            let obstacle_position = transform.translation.truncate();
+            let touching = collidable.within_reach(position, obstacle, obstacle_position, 1.)
+                && direction.dot(obstacle_position - position) > 0.;
+            let ahead = collidable
+                .sweep(position, direction * AVOID_LOOKAHEAD, obstacle, obstacle_position)
+                .is_some();
+            (touching || ahead).then_some(obstacle_position)
+        })
+    };
+
+    if direction == Vec2::ZERO {
+        return direction;
+    }
+
+    let Some(obstacle_position) = blocker(direction) else {
+        return direction;
+    };
+
+    // Positive turns left, which is away from an obstacle on the right
+    let side = if direction.perp_dot(obstacle_position - position) < 0. {
+        1.
+    } else {
+        -1.
+    };
+
+    [side, -side]
+        .into_iter()
+        .flat_map(|side| AVOID_ANGLES.map(|angle| Vec2::from_angle(angle * side).rotate(direction)))
+        .find(|&candidate| blocker(candidate).is_none())
+        .unwrap_or(direction)
+}
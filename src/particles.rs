@@ -0,0 +1,164 @@
+use std::collections::VecDeque;
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::combat::Dying;
+use crate::components::SpriteFallback;
+use crate::state::{GameSet, GameState};
+
+/// Particles thrown out by a single opponent death.
+const PARTICLES_PER_BURST: usize = 12;
+
+/// Most particles spawned in one frame, however many opponents die at once.
+const MAX_PARTICLES_PER_FRAME: usize = 60;
+
+/// Side length of a particle quad, in world units.
+const PARTICLE_SIZE: f32 = 5.;
+
+/// Range of initial particle speeds, in world units per second.
+const PARTICLE_SPEED: (f32, f32) = (80., 240.);
+
+/// Range of particle lifetimes, in seconds.
+const PARTICLE_LIFETIME: (f32, f32) = (0.3, 0.6);
+
+/// How quickly particles slow down, as an exponential decay rate per second.
+const PARTICLE_DRAG: f32 = 4.;
+
+/// Most decorative entities alive at once, across particles, afterimages and
+/// floating text.
+const MAX_DECORATIONS: usize = 300;
+
+/// Marks a purely visual entity that can be removed early without affecting
+/// gameplay, counted against the `EffectBudget`.
+#[derive(Component)]
+pub struct Decorative;
+
+/// Caps the number of live decorative entities so chaotic fights can't
+/// balloon the entity count.
+///
+/// `live` holds them in spawn order. Once there are more than `max`, the
+/// oldest are despawned first, since they are the closest to fading out
+/// anyway.
+#[derive(Resource)]
+pub struct EffectBudget {
+    pub max: usize,
+    live: VecDeque<Entity>,
+}
+
+impl Default for EffectBudget {
+    fn default() -> Self {
+        Self {
+            max: MAX_DECORATIONS,
+            live: VecDeque::new(),
+        }
+    }
+}
+
+/// Short lived decorative quad flying outwards and fading away.
+#[derive(Component)]
+pub struct Particle {
+    pub velocity: Vec2,
+    pub lifetime: Timer,
+}
+
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EffectBudget>()
+            .add_systems(
+                Update,
+                (spawn_death_particles, update_particles).in_set(GameSet::Cleanup),
+            )
+            // After everything that spawns or fades decorations this frame
+            .add_systems(PostUpdate, enforce_effect_budget);
+    }
+}
+
+/// Throw out a burst of particles in the opponent's color as it starts dying.
+///
+/// Bursts share a per frame budget, so a whole wave dying together gives
+/// fewer particles each instead of an unbounded number.
+fn spawn_death_particles(
+    mut commands: Commands,
+    dying: Query<(&Transform, &SpriteFallback), Added<Dying>>,
+) {
+    let bursts = dying.iter().count();
+
+    if bursts == 0 {
+        return;
+    }
+
+    let per_burst = PARTICLES_PER_BURST.min(MAX_PARTICLES_PER_FRAME / bursts);
+    let mut rng = rand::thread_rng();
+
+    for (transform, color) in &dying {
+        let origin = transform.translation.truncate();
+
+        for _ in 0..per_burst {
+            let angle = rng.gen_range(0.0..TAU);
+            let speed = rng.gen_range(PARTICLE_SPEED.0..=PARTICLE_SPEED.1);
+            let lifetime = rng.gen_range(PARTICLE_LIFETIME.0..=PARTICLE_LIFETIME.1);
+
+            commands.spawn((
+                Particle {
+                    velocity: Vec2::from_angle(angle) * speed,
+                    lifetime: Timer::from_seconds(lifetime, TimerMode::Once),
+                },
+                Decorative,
+                StateScoped(GameState::Playing),
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: color.0,
+                        custom_size: Some(Vec2::splat(PARTICLE_SIZE)),
+                        ..default()
+                    },
+                    transform: Transform {
+                        translation: origin.extend(4.),
+                        ..default()
+                    },
+                    ..default()
+                },
+            ));
+        }
+    }
+}
+
+/// Move particles along, fade them out and despawn them when their time is up.
+fn update_particles(
+    mut commands: Commands,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut particle, mut transform, mut sprite) in &mut particles {
+        if particle.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        transform.translation += (particle.velocity * time.delta_seconds()).extend(0.);
+        particle.velocity *= (-PARTICLE_DRAG * time.delta_seconds()).exp();
+        sprite.color.set_alpha(particle.lifetime.fraction_remaining());
+    }
+}
+
+/// Track new decorations and despawn the oldest ones while over budget.
+fn enforce_effect_budget(
+    mut commands: Commands,
+    mut budget: ResMut<EffectBudget>,
+    added: Query<Entity, Added<Decorative>>,
+    decorations: Query<(), With<Decorative>>,
+) {
+    // Forget the ones that faded out or went with the end of a run
+    budget.live.retain(|&entity| decorations.contains(entity));
+    budget.live.extend(&added);
+
+    while budget.live.len() > budget.max {
+        let Some(oldest) = budget.live.pop_front() else {
+            break;
+        };
+        commands.entity(oldest).despawn_recursive();
+    }
+}
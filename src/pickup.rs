@@ -0,0 +1,198 @@
+use bevy::prelude::*;
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+use rand::Rng;
+
+use crate::components::{Collidable, Health};
+use crate::effects::{EffectKind, TimedEffects};
+use crate::player::Player;
+use crate::rng::GameRng;
+use crate::state::{GameSet, GameState};
+use crate::world::{Obstacle, WorldBounds};
+
+/// Seconds between pickups appearing in the arena.
+const PICKUP_INTERVAL: f32 = 8.;
+
+/// Most pickups lying around at once.
+const MAX_PICKUPS: usize = 3;
+
+/// Collision radius of pickups.
+const PICKUP_RADIUS: f32 = 12.;
+
+/// Health restored by a health pack.
+const HEALTH_PACK_AMOUNT: f32 = 30.;
+
+/// Movement speed multiplier while a speed boost is active.
+const SPEED_BOOST_MULTIPLIER: f32 = 1.5;
+
+/// Seconds a speed boost lasts.
+const SPEED_BOOST_DURATION: f32 = 5.;
+
+/// Random spots tried when placing a pickup before giving up for this round.
+const PLACEMENT_ATTEMPTS: usize = 10;
+
+#[derive(Clone, Copy, Debug)]
+pub enum PickupKind {
+    HealthPack,
+    SpeedBoost,
+}
+
+/// Collected by the player on touch.
+#[derive(Component)]
+pub struct Pickup {
+    pub kind: PickupKind,
+}
+
+/// Drops a new pickup somewhere in the arena every so often.
+#[derive(Resource)]
+struct PickupSpawner {
+    timer: Timer,
+}
+
+impl Default for PickupSpawner {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(PICKUP_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+/// Mesh and materials shared by every pickup.
+#[derive(Resource)]
+struct PickupAssets {
+    mesh: Mesh2dHandle,
+    health_material: Handle<ColorMaterial>,
+    speed_material: Handle<ColorMaterial>,
+}
+
+pub struct PickupPlugin;
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PickupSpawner>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                (setup_pickup_assets, reset_pickup_spawner),
+            )
+            .add_systems(
+                Update,
+                (spawn_pickups, collect_pickups)
+                    .chain()
+                    .in_set(GameSet::Combat),
+            );
+    }
+}
+
+fn setup_pickup_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(PickupAssets {
+        mesh: meshes.add(Circle::new(PICKUP_RADIUS)).into(),
+        health_material: materials.add(Color::srgb(1.0, 0.4, 0.6)),
+        speed_material: materials.add(Color::srgb(0.3, 0.8, 1.0)),
+    });
+}
+
+fn reset_pickup_spawner(mut spawner: ResMut<PickupSpawner>) {
+    spawner.timer.reset();
+}
+
+/// Drop a random pickup at a free spot in the arena once the timer runs out.
+#[allow(clippy::too_many_arguments)]
+fn spawn_pickups(
+    mut commands: Commands,
+    mut spawner: ResMut<PickupSpawner>,
+    pickups: Query<(), With<Pickup>>,
+    obstacles: Query<(&Transform, &Collidable), With<Obstacle>>,
+    assets: Res<PickupAssets>,
+    bounds: Res<WorldBounds>,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
+) {
+    if !spawner.timer.tick(time.delta()).just_finished() || pickups.iter().len() >= MAX_PICKUPS {
+        return;
+    }
+
+    let rng = &mut rng.0;
+    let half = bounds.half_extents();
+
+    // Obstacles are solid, so a pickup inside one could never be collected
+    let position = (0..PLACEMENT_ATTEMPTS)
+        .map(|_| {
+            let position = Vec2::new(
+                rng.gen_range(-half.x..=half.x),
+                rng.gen_range(-half.y..=half.y),
+            );
+            bounds.clamp_world_position(position, PICKUP_RADIUS)
+        })
+        .find(|position| {
+            obstacles.iter().all(|(transform, collidable)| {
+                position.distance(transform.translation.truncate())
+                    > collidable.radius + PICKUP_RADIUS
+            })
+        });
+
+    let Some(position) = position else {
+        return;
+    };
+
+    let (kind, material) = if rng.gen_bool(0.5) {
+        (PickupKind::HealthPack, assets.health_material.clone())
+    } else {
+        (PickupKind::SpeedBoost, assets.speed_material.clone())
+    };
+
+    commands.spawn((
+        Pickup { kind },
+        StateScoped(GameState::Playing),
+        Collidable {
+            radius: PICKUP_RADIUS,
+        },
+        MaterialMesh2dBundle {
+            mesh: assets.mesh.clone(),
+            material,
+            transform: Transform {
+                translation: position.extend(0.8),
+                ..default()
+            },
+            ..default()
+        },
+    ));
+}
+
+/// Apply and remove every pickup the player is touching.
+fn collect_pickups(
+    mut commands: Commands,
+    mut player: Query<(&Transform, &Collidable, &mut Health, &mut TimedEffects), With<Player>>,
+    pickups: Query<(Entity, &Transform, &Collidable, &Pickup), Without<Player>>,
+) {
+    let Ok((player_transform, player_collidable, mut health, mut effects)) =
+        player.get_single_mut()
+    else {
+        return;
+    };
+
+    let player_position = player_transform.translation.truncate();
+
+    for (entity, transform, collidable, pickup) in &pickups {
+        let distance = player_position.distance(transform.translation.truncate());
+
+        if distance > player_collidable.radius + collidable.radius {
+            continue;
+        }
+
+        match pickup.kind {
+            PickupKind::HealthPack => {
+                health.current = (health.current + HEALTH_PACK_AMOUNT).min(health.max);
+            }
+            // Grabbing another while boosted only refreshes the duration
+            PickupKind::SpeedBoost => effects.add(
+                EffectKind::SpeedMultiplier(SPEED_BOOST_MULTIPLIER),
+                SPEED_BOOST_DURATION,
+            ),
+        }
+
+        commands.entity(entity).despawn_recursive();
+    }
+}
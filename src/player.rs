@@ -0,0 +1,956 @@
+use bevy::input::InputSystem;
+use bevy::math::vec3;
+use bevy::prelude::*;
+
+use crate::animation::{walk_cycle_layout, AnimationIndices, AnimationTimer};
+use crate::combat::{
+    resolve_collisions, AttackCooldown, AttackDamage, AttackFacing, Blocking, ChargingAttack,
+    CombatEvent, FireRate, Projectile, UltimateCharge, ATTACK_COOLDOWN, FIRE_INTERVAL,
+    ULTIMATE_COOLDOWN,
+};
+use crate::components::{
+    Collidable, CollisionLayers, Facing, Health, MovementSpeed, SpriteFallback, Stamina,
+    Velocity,
+};
+use crate::config::GameConfig;
+use crate::effects::{EffectKind, TimedEffects};
+use crate::particles::Decorative;
+use crate::state::{GameClock, GameSet, GameState, GameTimer, PauseState};
+use crate::world::WorldBounds;
+
+/// Default starting movement speed of the player, see `GameConfig`.
+pub const PLAYER_SPEED: f32 = 200.;
+
+/// How quickly the player speeds up towards the held direction, in units per second squared.
+const PLAYER_ACCELERATION: f32 = 1600.;
+
+/// How quickly the player slows down once input is released, in units per second squared.
+const PLAYER_FRICTION: f32 = 1200.;
+
+/// Movement speed multiplier while the player is blocking.
+const BLOCKING_SPEED_MULTIPLIER: f32 = 0.3;
+
+/// Movement speed multiplier while the player charges an attack.
+const CHARGING_SPEED_MULTIPLIER: f32 = 0.5;
+
+/// Distance covered by a dash.
+const DASH_DISTANCE: f32 = 300.;
+
+/// Seconds a dash takes to cover its distance.
+const DASH_DURATION: f32 = 0.15;
+
+/// Seconds of invincibility at the start of a dash, shorter than the dash so
+/// its tail can still be punished.
+const DASH_INVINCIBILITY: f32 = 0.1;
+
+/// Stamina used up by a dash.
+const DASH_STAMINA_COST: f32 = 30.;
+
+/// Largest amount of stamina the player can hold.
+const PLAYER_MAX_STAMINA: f32 = 100.;
+
+/// Stamina the player regains per second.
+const PLAYER_STAMINA_REGEN: f32 = 25.;
+
+/// Seconds after spending stamina before it starts refilling.
+const PLAYER_STAMINA_DELAY: f32 = 0.75;
+
+/// Seconds the player has to wait between dashes.
+const DASH_COOLDOWN: f32 = 1.;
+
+/// Default seconds at the end of an attack's recovery that a dash can cut
+/// short, see `GameConfig`.
+pub const DASH_CANCEL_WINDOW: f32 = 0.25;
+
+/// Opacity of a dash afterimage when it is left behind.
+const AFTERIMAGE_ALPHA: f32 = 0.5;
+
+/// Seconds a dash afterimage takes to fade out completely.
+const AFTERIMAGE_LIFETIME: f32 = 0.25;
+
+/// Sideways component the facing needs before the sprite turns around, so
+/// mostly vertical movement doesn't flip it back and forth.
+const FLIP_THRESHOLD: f32 = 0.1;
+
+/// Default experience earned per opponent defeated, see `GameConfig`.
+pub const XP_PER_KILL: f32 = 10.;
+
+/// Default experience needed to reach level 2, see `GameConfig`.
+pub const XP_TO_LEVEL: f32 = 30.;
+
+/// Default factor the experience needed grows by with every level, see
+/// `GameConfig`.
+pub const XP_GROWTH: f32 = 1.5;
+
+/// Default health the player regains per second out of combat, see
+/// `GameConfig`.
+pub const HEALTH_REGEN_RATE: f32 = 4.;
+
+/// Default seconds without taking damage before health starts refilling, see
+/// `GameConfig`.
+pub const HEALTH_REGEN_DELAY: f32 = 5.;
+
+/// Maximum health gained per level.
+const LEVEL_HEALTH_BONUS: f32 = 20.;
+
+/// Melee damage gained per level.
+const LEVEL_DAMAGE_BONUS: f32 = 5.;
+
+/// Walk cycle sprite sheet used for the player, relative to the assets folder.
+pub const PLAYER_SPRITE: &str = "sprites/player.png";
+
+#[derive(Component)]
+pub struct Player;
+
+/// A short burst of movement, with `cooldown` tracking when it can be used again.
+#[derive(Component)]
+pub struct Dash {
+    pub timer: GameTimer,
+    pub cooldown: GameTimer,
+    direction: Vec2,
+}
+
+impl Dash {
+    /// Create a dash that is not running and ready to use straight away.
+    fn new(duration: f32, cooldown: f32) -> Self {
+        Self {
+            timer: GameTimer::new_finished(duration, TimerMode::Once),
+            cooldown: GameTimer::new_finished(cooldown, TimerMode::Once),
+            direction: Vec2::ZERO,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.timer.finished()
+    }
+
+    /// Advance the running dash and its cooldown.
+    fn tick(&mut self, clock: &GameClock) {
+        self.timer.tick(clock);
+        self.cooldown.tick(clock);
+    }
+
+    /// Whether the cooldown has run out since the last dash started.
+    pub fn is_ready(&self) -> bool {
+        self.cooldown.finished()
+    }
+
+    /// Start dashing towards `direction`, restarting the cooldown.
+    ///
+    /// Callers check `is_ready` first, so a dash is never restarted midway.
+    fn start(&mut self, direction: Vec2) {
+        self.direction = direction;
+        self.timer.reset();
+        self.cooldown.reset();
+    }
+}
+
+/// Counts up from the player's last hit, so health only refills once the
+/// player has been out of combat for a while, see `regen_health`.
+#[derive(Component)]
+pub struct LastDamaged {
+    pub timer: Timer,
+}
+
+impl LastDamaged {
+    pub fn new(delay: f32) -> Self {
+        Self {
+            timer: Timer::from_seconds(delay, TimerMode::Once),
+        }
+    }
+}
+
+/// Experience the player has built up towards its next level.
+#[derive(Component)]
+pub struct Experience {
+    pub current: f32,
+    pub level: u32,
+    /// Experience needed for the next level, counted from zero.
+    pub to_next: f32,
+}
+
+impl Experience {
+    fn new(to_next: f32) -> Self {
+        Self {
+            current: 0.,
+            level: 1,
+            to_next,
+        }
+    }
+
+    /// Add `amount` of experience, returning how many levels it was worth.
+    ///
+    /// Leftover experience carries over, and each level needs `growth` times
+    /// as much as the one before.
+    fn gain(&mut self, amount: f32, growth: f32) -> u32 {
+        self.current += amount;
+        let mut levels = 0;
+
+        while self.current >= self.to_next {
+            self.current -= self.to_next;
+            self.to_next *= growth;
+            self.level += 1;
+            levels += 1;
+        }
+
+        levels
+    }
+}
+
+/// Sent whenever the player reaches a new level.
+#[derive(Event)]
+pub struct LevelUp {
+    pub level: u32,
+}
+
+/// Fading copy of the player sprite left behind along a dash.
+///
+/// Purely decorative, without a `Collidable`. It despawns itself once the
+/// timer runs out, and is scoped to the run in case that never happens.
+#[derive(Component)]
+struct Afterimage {
+    timer: Timer,
+}
+
+/// Keys used to control the player.
+#[derive(Resource)]
+pub struct KeyBindings {
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub attack: KeyCode,
+    pub dash: KeyCode,
+    pub ultimate: KeyCode,
+    pub time_slow: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            left: KeyCode::KeyA,
+            right: KeyCode::KeyD,
+            up: KeyCode::KeyW,
+            down: KeyCode::KeyS,
+            attack: KeyCode::Space,
+            dash: KeyCode::ShiftLeft,
+            ultimate: KeyCode::KeyQ,
+            time_slow: KeyCode::KeyE,
+        }
+    }
+}
+
+/// Player input gathered once per frame for the fixed step movement systems.
+///
+/// Fixed steps can run several times in one frame or not at all, so a
+/// `just_pressed` read there could fire twice or be missed entirely. Presses
+/// are latched here instead and stay set until a fixed step consumes them.
+#[derive(Resource, Default)]
+pub struct PlayerInput {
+    /// Held movement direction, never longer than one.
+    pub movement: Vec2,
+    /// The dash key was pressed and no fixed step has handled it yet.
+    pub dash: bool,
+}
+
+pub struct PlayerPlugin;
+
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<KeyBindings>()
+            .init_resource::<PlayerInput>()
+            .add_event::<LevelUp>()
+            .add_systems(OnEnter(GameState::Playing), spawn_player)
+            // Read before the fixed steps of the same frame run
+            .add_systems(
+                PreUpdate,
+                read_player_input
+                    .after(InputSystem)
+                    .run_if(in_state(PauseState::Running)),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    (move_player, dash, flip_player_sprite)
+                        .chain()
+                        .in_set(GameSet::Movement),
+                    clamp_to_world
+                        .after(resolve_collisions)
+                        .in_set(GameSet::Physics),
+                ),
+            )
+            .add_systems(
+                Update,
+                (gain_experience, regen_health, check_player_death).in_set(GameSet::Cleanup),
+            )
+            // Driven by virtual time like the sprite animations, so the trail
+            // freezes while paused
+            .add_systems(Update, fade_afterimages);
+    }
+}
+
+fn spawn_player(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    config: Res<GameConfig>,
+) {
+    // Related components are grouped into nested tuples to stay within the
+    // bundle tuple size limit
+    commands.spawn((
+        Player,
+        StateScoped(GameState::Playing),
+        (
+            Collidable {
+                radius: config.collision_radius,
+            },
+            CollisionLayers::PLAYER,
+        ),
+        (
+            Health::new(config.max_health),
+            LastDamaged::new(config.health_regen_delay),
+            Experience::new(config.xp_to_level),
+        ),
+        Stamina::new(PLAYER_MAX_STAMINA, PLAYER_STAMINA_REGEN, PLAYER_STAMINA_DELAY),
+        MovementSpeed(config.player_speed),
+        TimedEffects::default(),
+        Velocity::default(),
+        Facing::default(),
+        (
+            AttackCooldown::new(ATTACK_COOLDOWN),
+            AttackDamage(config.attack_damage),
+            AttackFacing::default(),
+            FireRate::new(FIRE_INTERVAL),
+            UltimateCharge::new(ULTIMATE_COOLDOWN),
+        ),
+        Dash::new(DASH_DURATION, DASH_COOLDOWN),
+        SpriteFallback(Color::srgb(0.0, 1.0, 0.0)),
+        SpriteBundle {
+            texture: asset_server.load(PLAYER_SPRITE),
+            sprite: Sprite {
+                // Sized to the collision circle so visuals and physics line up
+                custom_size: Some(Vec2::splat(config.collision_radius * 2.)),
+                ..default()
+            },
+            transform: Transform {
+                translation: vec3(0., 0., 2.),
+                ..default()
+            },
+            ..default()
+        },
+        // Walk cycle
+        (
+            TextureAtlas {
+                layout: layouts.add(walk_cycle_layout()),
+                index: 0,
+            },
+            AnimationIndices::walk_cycle(),
+            AnimationTimer::default(),
+        ),
+    ));
+}
+
+/// Accelerate the player towards the held direction and move it along its velocity.
+///
+/// Blocking slows the player to a shuffle.
+#[allow(clippy::type_complexity)]
+fn move_player(
+    mut player: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &mut Facing,
+            &MovementSpeed,
+            &TimedEffects,
+            Has<Blocking>,
+            Option<&ChargingAttack>,
+        ),
+        With<Player>,
+    >,
+    time: Res<Time>,
+    input: Res<PlayerInput>,
+) {
+    let Ok((mut player_transform, mut velocity, mut facing, speed, effects, blocking, charging)) =
+        player.get_single_mut()
+    else {
+        return;
+    };
+
+    let direction = input.movement;
+
+    // Only turn on actual input, so letting go keeps the last facing
+    if let Some(heading) = direction.try_normalize() {
+        facing.0 = heading;
+    }
+
+    let mut speed = speed.0 * effects.speed_multiplier();
+    if blocking {
+        speed *= BLOCKING_SPEED_MULTIPLIER;
+    }
+    // Quick taps don't slow the player down, only holding past the threshold
+    if charging.is_some_and(ChargingAttack::is_charged) {
+        speed *= CHARGING_SPEED_MULTIPLIER;
+    }
+
+    velocity.0 = accelerate(velocity.0, direction, speed, time.delta_seconds());
+    player_transform.translation += (velocity.0 * time.delta_seconds()).extend(0.);
+}
+
+/// Mirror the player sprite so it looks the way the player is facing.
+///
+/// The texture faces right. Facing straight up or down leaves the sprite as
+/// it was, so it keeps the last horizontal direction.
+#[allow(clippy::type_complexity)]
+fn flip_player_sprite(
+    mut player: Query<(&mut Sprite, &Facing), (With<Player>, Changed<Facing>)>,
+) {
+    let Ok((mut sprite, facing)) = player.get_single_mut() else {
+        return;
+    };
+
+    if facing.0.x.abs() >= FLIP_THRESHOLD {
+        sprite.flip_x = facing.0.x < 0.;
+    }
+}
+
+/// Velocity after one frame of accelerating towards the input direction, or of
+/// friction when there's no input.
+///
+/// The result is clamped to `speed` whatever the input looks like, so no input
+/// source (diagonal keys, a stick pushed into a corner) can move the player
+/// faster than its speed.
+fn accelerate(velocity: Vec2, direction: Vec2, speed: f32, delta: f32) -> Vec2 {
+    let (target, rate) = if direction == Vec2::ZERO {
+        (Vec2::ZERO, PLAYER_FRICTION)
+    } else {
+        (direction * speed, PLAYER_ACCELERATION)
+    };
+
+    // Step towards the target without overshooting it
+    let change = (target - velocity).clamp_length_max(rate * delta);
+    (velocity + change).clamp_length_max(speed)
+}
+
+/// Latch this frame's input for the fixed step systems.
+fn read_player_input(
+    mut input: ResMut<PlayerInput>,
+    kb_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+) {
+    input.movement = movement_input(&kb_input, &bindings, &gamepads, &axes);
+    input.dash |= kb_input.just_pressed(bindings.dash);
+}
+
+/// Read the movement direction currently held on the keyboard or left stick.
+///
+/// Keyboard input is always full length, while a partially tilted stick gives
+/// a proportionally shorter direction. The result is never longer than one.
+fn movement_input(
+    kb_input: &ButtonInput<KeyCode>,
+    bindings: &KeyBindings,
+    gamepads: &Gamepads,
+    axes: &Axis<GamepadAxis>,
+) -> Vec2 {
+    let mut direction = Vec2::ZERO;
+
+    if kb_input.pressed(bindings.up) {
+        direction.y += 1.;
+    }
+
+    if kb_input.pressed(bindings.down) {
+        direction.y -= 1.;
+    }
+
+    if kb_input.pressed(bindings.left) {
+        direction.x -= 1.;
+    }
+
+    if kb_input.pressed(bindings.right) {
+        direction.x += 1.;
+    }
+
+    // Disconnected pads drop out of Gamepads, leaving just the keyboard
+    let stick: Vec2 = gamepads
+        .iter()
+        .map(|gamepad| {
+            let x = axes
+                .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX))
+                .unwrap_or(0.);
+            let y = axes
+                .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY))
+                .unwrap_or(0.);
+            Vec2::new(x, y)
+        })
+        .sum();
+
+    (direction.normalize_or_zero() + stick).clamp_length_max(1.)
+}
+
+/// Dash in the held movement direction when the player presses the dash key.
+///
+/// Each step is swept against opponents and obstacles and stops at the first
+/// one it touches, so a dash can't skip over a thin target. resolve_collisions
+/// runs afterwards and settles the contact. Starting one makes the player
+/// briefly invincible. Every step of a dash leaves an afterimage behind. A new
+/// dash can't start while blocking.
+///
+/// Dashing near the end of an attack's recovery cancels the rest of it, so
+/// the player can attack again as soon as the dash starts. It still costs a
+/// regular dash, cooldown and stamina included.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn dash(
+    mut commands: Commands,
+    mut player: Query<
+        (
+            &mut Transform,
+            (&Collidable, &CollisionLayers),
+            &mut Dash,
+            &mut Stamina,
+            &mut TimedEffects,
+            &mut AttackCooldown,
+            &Sprite,
+            &Handle<Image>,
+            Option<&TextureAtlas>,
+            Has<Blocking>,
+        ),
+        With<Player>,
+    >,
+    // Walking into a projectile shouldn't cut a dash short
+    bodies: Query<
+        (&Transform, &Collidable, &CollisionLayers),
+        (Without<Player>, Without<Projectile>),
+    >,
+    time: Res<Time>,
+    clock: GameClock,
+    mut input: ResMut<PlayerInput>,
+    config: Res<GameConfig>,
+) {
+    let Ok((
+        mut transform,
+        (collidable, layers),
+        mut dash,
+        mut stamina,
+        mut effects,
+        mut attack_cooldown,
+        sprite,
+        texture,
+        atlas,
+        blocking,
+    )) = player.get_single_mut()
+    else {
+        return;
+    };
+
+    dash.tick(&clock);
+
+    // Dashes always go the full distance, however far the stick is tilted
+    let direction = input.movement.normalize_or_zero();
+
+    // A press is used up by the first step that sees it, even if it can't dash
+    if std::mem::take(&mut input.dash)
+        && !blocking
+        && direction != Vec2::ZERO
+        && dash.is_ready()
+        && stamina.try_spend(DASH_STAMINA_COST)
+    {
+        dash.start(direction);
+        effects.add(EffectKind::Invincible, DASH_INVINCIBILITY);
+
+        if attack_cooldown.can_cancel(config.dash_cancel_window) {
+            attack_cooldown.cancel();
+        }
+    }
+
+    if dash.is_active() {
+        spawn_afterimage(&mut commands, &transform, sprite, texture, atlas);
+
+        let step = dash.direction * DASH_DISTANCE / DASH_DURATION * time.delta_seconds();
+        let position = transform.translation.truncate();
+
+        let contact = bodies
+            .iter()
+            .filter(|(_, _, other_layers)| layers.interacts(other_layers))
+            .filter_map(|(other_transform, other, _)| {
+                collidable.sweep(position, step, other, other_transform.translation.truncate())
+            })
+            .fold(1., f32::min);
+
+        transform.translation += (step * contact).extend(0.);
+    }
+}
+
+/// Leave a see-through copy of the player's current frame where it stands.
+fn spawn_afterimage(
+    commands: &mut Commands,
+    transform: &Transform,
+    sprite: &Sprite,
+    texture: &Handle<Image>,
+    atlas: Option<&TextureAtlas>,
+) {
+    let mut sprite = sprite.clone();
+    sprite.color.set_alpha(AFTERIMAGE_ALPHA);
+
+    let mut afterimage = commands.spawn((
+        Afterimage {
+            timer: Timer::from_seconds(AFTERIMAGE_LIFETIME, TimerMode::Once),
+        },
+        Decorative,
+        StateScoped(GameState::Playing),
+        SpriteBundle {
+            texture: texture.clone(),
+            sprite,
+            transform: Transform {
+                // Just behind the player, so the trail never covers it
+                translation: transform.translation - Vec3::Z * 0.1,
+                ..*transform
+            },
+            ..default()
+        },
+    ));
+
+    // Missing sprites fall back to a plain color without an atlas
+    if let Some(atlas) = atlas {
+        afterimage.insert(atlas.clone());
+    }
+}
+
+/// Fade dash afterimages out and despawn them once they are gone.
+fn fade_afterimages(
+    mut commands: Commands,
+    mut afterimages: Query<(Entity, &mut Afterimage, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut afterimage, mut sprite) in &mut afterimages {
+        if afterimage.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        sprite
+            .color
+            .set_alpha(AFTERIMAGE_ALPHA * afterimage.timer.fraction_remaining());
+    }
+}
+
+/// Keep the player fully inside the arena, stopping any movement into the edge.
+fn clamp_to_world(
+    mut player: Query<(&mut Transform, &mut Velocity, &Collidable), With<Player>>,
+    bounds: Res<WorldBounds>,
+) {
+    let Ok((mut transform, mut velocity, collidable)) = player.get_single_mut() else {
+        return;
+    };
+
+    let position = transform.translation.truncate();
+    let clamped = bounds.clamp_world_position(position, collidable.radius);
+
+    if clamped.x != position.x {
+        velocity.0.x = 0.;
+    }
+
+    if clamped.y != position.y {
+        velocity.0.y = 0.;
+    }
+
+    transform.translation = clamped.extend(transform.translation.z);
+}
+
+/// Earn experience for every opponent defeated, growing stronger and healing
+/// to full on each level up.
+fn gain_experience(
+    mut player: Query<(&mut Experience, &mut Health, &mut AttackDamage), With<Player>>,
+    mut combat_events: EventReader<CombatEvent>,
+    mut level_ups: EventWriter<LevelUp>,
+    config: Res<GameConfig>,
+) {
+    let Ok((mut experience, mut health, mut damage)) = player.get_single_mut() else {
+        return;
+    };
+
+    let kills = combat_events
+        .read()
+        .filter(|event| matches!(event, CombatEvent::EnemyKilled))
+        .count();
+
+    if kills == 0 {
+        return;
+    }
+
+    let levels = experience.gain(kills as f32 * config.xp_per_kill, config.xp_growth);
+
+    if levels == 0 {
+        return;
+    }
+
+    health.max += LEVEL_HEALTH_BONUS * levels as f32;
+    health.current = health.max;
+    damage.0 += LEVEL_DAMAGE_BONUS * levels as f32;
+
+    info!("Reached level {}", experience.level);
+    level_ups.send(LevelUp {
+        level: experience.level,
+    });
+}
+
+/// Slowly refill the player's health once it has gone long enough without
+/// getting hit. Any hit starts the wait over.
+fn regen_health(
+    mut combat_events: EventReader<CombatEvent>,
+    mut player: Query<(&mut Health, &mut LastDamaged), With<Player>>,
+    config: Res<GameConfig>,
+    time: Res<Time>,
+) {
+    let Ok((mut health, mut last_damaged)) = player.get_single_mut() else {
+        return;
+    };
+
+    let hit = combat_events
+        .read()
+        .any(|event| matches!(event, CombatEvent::PlayerHit(_)));
+
+    if hit {
+        last_damaged.timer.reset();
+        return;
+    }
+
+    // A player already at zero is about to die, not to heal
+    if health.current <= 0. || !last_damaged.timer.tick(time.delta()).finished() {
+        return;
+    }
+
+    let regen = config.health_regen_rate * time.delta_seconds();
+    health.current = (health.current + regen).min(health.max);
+}
+
+/// End the game once the player's health runs out.
+fn check_player_death(
+    player: Query<&Health, With<Player>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Ok(health) = player.get_single() else {
+        return;
+    };
+
+    if health.current <= 0. {
+        next_state.set(GameState::GameOver);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::combat::{tick_attack_cooldown, AttackState};
+    use crate::effects::EffectsPlugin;
+    use crate::state::tests::playing_app;
+
+    /// One physics step.
+    const DELTA: f32 = 1. / 60.;
+
+    /// Velocity after holding `direction` from a standstill for two seconds.
+    fn top_velocity(direction: Vec2) -> Vec2 {
+        (0..120).fold(Vec2::ZERO, |velocity, _| {
+            accelerate(velocity, direction, PLAYER_SPEED, DELTA)
+        })
+    }
+
+    #[test]
+    fn diagonal_top_speed_matches_straight() {
+        let straight = top_velocity(Vec2::new(1., 0.)).length();
+        let diagonal = top_velocity(Vec2::new(1., 1.)).length();
+
+        assert!((straight - PLAYER_SPEED).abs() < 1e-3);
+        assert!((diagonal - straight).abs() < 1e-3);
+    }
+
+    /// Headless app running `dash` in 10ms frames, with a player holding
+    /// movement to the right and ready to dash.
+    fn dash_app() -> (App, Entity) {
+        let mut app = playing_app(Duration::from_millis(10));
+        app.add_plugins(EffectsPlugin)
+            .insert_resource(PlayerInput {
+                movement: Vec2::X,
+                dash: false,
+            })
+            .add_systems(Update, dash.before(GameSet::Combat));
+
+        let player = app
+            .world_mut()
+            .spawn((
+                Player,
+                Transform::default(),
+                (Collidable { radius: 25. }, CollisionLayers::PLAYER),
+                Dash::new(DASH_DURATION, DASH_COOLDOWN),
+                Stamina::new(PLAYER_MAX_STAMINA, PLAYER_STAMINA_REGEN, PLAYER_STAMINA_DELAY),
+                TimedEffects::default(),
+                AttackCooldown::new(ATTACK_COOLDOWN),
+                Sprite::default(),
+                Handle::<Image>::default(),
+            ))
+            .id();
+
+        (app, player)
+    }
+
+    /// Swing, wait `frames` 10ms frames into the swing and dash on the next
+    /// one. Returns the attack state and stamina after that frame.
+    fn dash_during_swing(frames: u32, stamina: f32) -> (AttackState, f32) {
+        let (mut app, player) = dash_app();
+        // Ticked before the dash, as in a real frame
+        app.add_systems(Update, tick_attack_cooldown.before(dash));
+
+        let mut entity = app.world_mut().entity_mut(player);
+        entity.get_mut::<AttackCooldown>().unwrap().timer.reset();
+        entity.get_mut::<Stamina>().unwrap().current = stamina;
+
+        for _ in 0..frames {
+            app.update();
+        }
+        app.world_mut().resource_mut::<PlayerInput>().dash = true;
+        app.update();
+
+        let entity = app.world().entity(player);
+        let cooldown = entity.get::<AttackCooldown>().unwrap();
+        (cooldown.state(false), entity.get::<Stamina>().unwrap().current)
+    }
+
+    #[test]
+    fn dash_cancels_the_end_of_a_recovery() {
+        // 0.4 seconds into a 0.5 second swing, inside the last 0.25
+        let (state, stamina) = dash_during_swing(39, PLAYER_MAX_STAMINA);
+
+        assert_eq!(state, AttackState::Ready);
+        assert_eq!(stamina, PLAYER_MAX_STAMINA - DASH_STAMINA_COST);
+    }
+
+    #[test]
+    fn dash_before_the_cancel_window_leaves_the_recovery() {
+        // 0.15 seconds in, recovering but with 0.35 seconds still to go
+        let (state, stamina) = dash_during_swing(14, PLAYER_MAX_STAMINA);
+
+        assert_eq!(state, AttackState::Recovery);
+        // The dash itself still goes off
+        assert_eq!(stamina, PLAYER_MAX_STAMINA - DASH_STAMINA_COST);
+    }
+
+    #[test]
+    fn dash_cancel_needs_stamina() {
+        let (state, stamina) = dash_during_swing(39, DASH_STAMINA_COST - 1.);
+
+        assert_eq!(state, AttackState::Recovery);
+        assert_eq!(stamina, DASH_STAMINA_COST - 1.);
+    }
+
+    #[test]
+    fn dash_is_invincible_then_cools_down() {
+        let (mut app, player) = dash_app();
+        app.world_mut().resource_mut::<PlayerInput>().dash = true;
+
+        // Frames of 10ms run so far, counting the one the dash starts in
+        let mut frames = 0;
+        let mut run_until = |app: &mut App, until: u32| {
+            while frames < until {
+                app.update();
+                frames += 1;
+            }
+        };
+        let invincible = |app: &App| {
+            app.world().get::<TimedEffects>(player).unwrap().is_invincible()
+        };
+        let ready = |app: &App| app.world().get::<Dash>(player).unwrap().is_ready();
+        let active = |app: &App| app.world().get::<Dash>(player).unwrap().is_active();
+        let stamina = |app: &App| app.world().get::<Stamina>(player).unwrap().current;
+        let press_dash = |app: &mut App| app.world_mut().resource_mut::<PlayerInput>().dash = true;
+
+        run_until(&mut app, 1);
+        assert!(active(&app));
+        assert!(invincible(&app));
+        assert!(!ready(&app));
+        let stamina_after_dash = stamina(&app);
+        assert_eq!(stamina_after_dash, PLAYER_MAX_STAMINA - DASH_STAMINA_COST);
+
+        run_until(&mut app, (DASH_INVINCIBILITY * 100.) as u32 - 2);
+        assert!(invincible(&app));
+
+        run_until(&mut app, (DASH_INVINCIBILITY * 100.) as u32 + 2);
+        assert!(!invincible(&app));
+
+        // A second press halfway through the cooldown is used up without dashing
+        run_until(&mut app, (DASH_COOLDOWN * 50.) as u32);
+        press_dash(&mut app);
+        run_until(&mut app, (DASH_COOLDOWN * 50.) as u32 + 1);
+        assert!(!active(&app));
+        assert!(!app.world().resource::<PlayerInput>().dash);
+        assert_eq!(stamina(&app), stamina_after_dash);
+
+        run_until(&mut app, (DASH_COOLDOWN * 100.) as u32 - 5);
+        assert!(!ready(&app));
+
+        run_until(&mut app, (DASH_COOLDOWN * 100.) as u32 + 5);
+        assert!(ready(&app));
+
+        // Once the cooldown is over the next press dashes again
+        press_dash(&mut app);
+        run_until(&mut app, (DASH_COOLDOWN * 100.) as u32 + 6);
+        assert!(active(&app));
+        assert_eq!(stamina(&app), stamina_after_dash - DASH_STAMINA_COST);
+    }
+
+    #[test]
+    fn lag_spike_cant_carry_the_player_through_an_opponent() {
+        use crate::combat::Contact;
+        use crate::debug::{CollisionCorrections, DebugOverlay};
+        use crate::opponent::Opponent;
+        use crate::spatial::{update_spatial_grid, SpatialGrid};
+
+        // Every frame is a two second stall
+        let mut app = playing_app(Duration::from_secs(2));
+        app.add_event::<Contact>()
+            .init_resource::<SpatialGrid>()
+            .init_resource::<DebugOverlay>()
+            .init_resource::<CollisionCorrections>()
+            .init_resource::<PlayerInput>()
+            .add_systems(FixedUpdate, move_player.in_set(GameSet::Movement))
+            .add_systems(
+                FixedUpdate,
+                (update_spatial_grid, resolve_collisions)
+                    .chain()
+                    .in_set(GameSet::Physics),
+            );
+
+        let player = app
+            .world_mut()
+            .spawn((
+                Player,
+                Transform::default(),
+                Velocity::default(),
+                Facing::default(),
+                MovementSpeed(PLAYER_SPEED),
+                TimedEffects::default(),
+                Collidable { radius: 25. },
+                CollisionLayers::PLAYER,
+            ))
+            .id();
+        let opponent = app
+            .world_mut()
+            .spawn((
+                Opponent,
+                Transform::from_xyz(100., 0., 0.),
+                Collidable { radius: 25. },
+                CollisionLayers::ENEMY,
+            ))
+            .id();
+
+        app.world_mut().resource_mut::<PlayerInput>().movement = Vec2::X;
+
+        let x = |app: &App, entity| app.world().get::<Transform>(entity).unwrap().translation.x;
+        for _ in 0..5 {
+            app.update();
+            assert!(x(&app, player) > 0., "the player never moved");
+            assert!(x(&app, player) < x(&app, opponent), "the player went through");
+        }
+    }
+}
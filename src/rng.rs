@@ -0,0 +1,41 @@
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::settings::Settings;
+use crate::state::GameState;
+
+/// Source of every random gameplay decision, like where waves come in and
+/// which pickups drop.
+///
+/// It is reseeded at the start of every run, from `Settings::seed` when one is
+/// set, so a run can be replayed exactly by setting the seed it logged.
+/// ChaCha8 is used rather than `StdRng`, whose algorithm can change with any
+/// rand release, so a logged seed keeps replaying the same run.
+/// Purely visual randomness, like particles, stays on the thread RNG so
+/// effects spawned from frame rate dependent systems don't shift the
+/// gameplay sequence.
+#[derive(Resource)]
+pub struct GameRng(pub ChaCha8Rng);
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self(ChaCha8Rng::from_entropy())
+    }
+}
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameRng>()
+            .add_systems(OnEnter(GameState::Playing), seed_game_rng);
+    }
+}
+
+/// Seed the RNG for a new run and log the seed so the run can be repeated.
+fn seed_game_rng(mut rng: ResMut<GameRng>, settings: Res<Settings>) {
+    let seed = settings.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    info!("Starting run with seed {seed}");
+    rng.0 = ChaCha8Rng::seed_from_u64(seed);
+}
@@ -0,0 +1,152 @@
+use std::time::Duration;
+
+use bevy::input::gamepad::{GamepadRumbleIntensity, GamepadRumbleRequest};
+use bevy::prelude::*;
+
+use crate::combat::CombatEvent;
+use crate::state::{GameSet, GameState, PauseState};
+
+/// Seconds the strong motor rumbles for when the player takes damage.
+const PLAYER_HIT_RUMBLE_TIME: f32 = 0.2;
+
+/// Damage taken that rumbles at full strength. Lighter hits rumble less.
+const PLAYER_HIT_FULL_DAMAGE: f32 = 25.;
+
+/// Seconds the weak motor rumbles for when the player lands a hit.
+const ENEMY_HIT_RUMBLE_TIME: f32 = 0.08;
+
+/// Damage dealt that rumbles at `ENEMY_HIT_MAX_INTENSITY`.
+const ENEMY_HIT_FULL_DAMAGE: f32 = 40.;
+
+/// Strongest rumble for landing a hit, kept below taking one.
+const ENEMY_HIT_MAX_INTENSITY: f32 = 0.5;
+
+/// Seconds and intensity of the weak motor rumble when an opponent dies.
+const ENEMY_KILLED_RUMBLE: (f32, f32) = (0.12, 0.4);
+
+/// Weakest rumble worth sending, so grazing hits can still be felt.
+const MIN_RUMBLE_INTENSITY: f32 = 0.15;
+
+/// The rumble currently playing on every connected gamepad.
+///
+/// Only one rumble plays at a time. A new one replaces it when it is at least
+/// as strong or the old one is over, and is dropped otherwise, so a flurry of
+/// hits never piles up requests.
+#[derive(Resource)]
+struct Rumble {
+    /// Intensity of the stronger motor.
+    strength: f32,
+    /// Runs on real time, the motors don't slow down with the game.
+    timer: Timer,
+}
+
+impl Default for Rumble {
+    /// Nothing playing.
+    fn default() -> Self {
+        let mut timer = Timer::from_seconds(0., TimerMode::Once);
+        timer.tick(Duration::ZERO);
+
+        Self {
+            strength: 0.,
+            timer,
+        }
+    }
+}
+
+impl Rumble {
+    fn strength(intensity: GamepadRumbleIntensity) -> f32 {
+        intensity.strong_motor.max(intensity.weak_motor)
+    }
+
+    /// Whether `intensity` should take over from the current rumble.
+    fn replaced_by(&self, intensity: GamepadRumbleIntensity) -> bool {
+        self.timer.finished() || Self::strength(intensity) >= self.strength
+    }
+}
+
+pub struct RumblePlugin;
+
+impl Plugin for RumblePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rumble>()
+            // After combat has sent this frame's events
+            .add_systems(Update, rumble_on_events.in_set(GameSet::Cleanup))
+            // Nothing keeps shaking the pad in the pause menu or after a run
+            .add_systems(OnEnter(PauseState::Paused), stop_rumble)
+            .add_systems(OnExit(GameState::Playing), stop_rumble);
+    }
+}
+
+/// Rumble connected gamepads for the strongest combat event of the frame.
+///
+/// Taking damage uses the strong motor, landing hits and kills the weak one,
+/// both scaled by the damage. Without a gamepad connected nothing is sent.
+fn rumble_on_events(
+    mut events: EventReader<CombatEvent>,
+    mut rumble: ResMut<Rumble>,
+    mut requests: EventWriter<GamepadRumbleRequest>,
+    gamepads: Res<Gamepads>,
+    time: Res<Time<Real>>,
+) {
+    rumble.timer.tick(time.delta());
+
+    let strongest = events
+        .read()
+        .map(|event| match *event {
+            CombatEvent::PlayerHit(damage) => (
+                PLAYER_HIT_RUMBLE_TIME,
+                GamepadRumbleIntensity::strong_motor(scaled(damage, PLAYER_HIT_FULL_DAMAGE, 1.)),
+            ),
+            CombatEvent::EnemyHit(damage) => (
+                ENEMY_HIT_RUMBLE_TIME,
+                GamepadRumbleIntensity::weak_motor(scaled(
+                    damage,
+                    ENEMY_HIT_FULL_DAMAGE,
+                    ENEMY_HIT_MAX_INTENSITY,
+                )),
+            ),
+            CombatEvent::EnemyKilled => (
+                ENEMY_KILLED_RUMBLE.0,
+                GamepadRumbleIntensity::weak_motor(ENEMY_KILLED_RUMBLE.1),
+            ),
+        })
+        .max_by(|(_, a), (_, b)| Rumble::strength(*a).total_cmp(&Rumble::strength(*b)));
+
+    let Some((seconds, intensity)) = strongest else {
+        return;
+    };
+
+    if !rumble.replaced_by(intensity) {
+        return;
+    }
+
+    rumble.strength = Rumble::strength(intensity);
+    rumble.timer = Timer::from_seconds(seconds, TimerMode::Once);
+
+    // Rumbles added on top of each other add up, so the old one goes first
+    for gamepad in gamepads.iter() {
+        requests.send(GamepadRumbleRequest::Stop { gamepad });
+        requests.send(GamepadRumbleRequest::Add {
+            duration: Duration::from_secs_f32(seconds),
+            intensity,
+            gamepad,
+        });
+    }
+}
+
+/// Motor intensity for `damage`, reaching `max` at `full_damage`.
+fn scaled(damage: f32, full_damage: f32, max: f32) -> f32 {
+    (damage / full_damage * max).clamp(MIN_RUMBLE_INTENSITY, max)
+}
+
+fn stop_rumble(
+    mut rumble: ResMut<Rumble>,
+    mut requests: EventWriter<GamepadRumbleRequest>,
+    gamepads: Res<Gamepads>,
+) {
+    *rumble = Rumble::default();
+
+    for gamepad in gamepads.iter() {
+        requests.send(GamepadRumbleRequest::Stop { gamepad });
+    }
+}
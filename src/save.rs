@@ -0,0 +1,311 @@
+use std::fs;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::boss::{boss_max_health, spawn_boss, Boss, BossFight, BossPhase};
+use crate::combat::{AttackDamage, Dying, Score};
+use crate::components::Health;
+use crate::config::GameConfig;
+use crate::difficulty::Difficulty;
+use crate::opponent::{
+    spawn_opponent, EnemyType, Opponent, OpponentSprites, Patrol, SpawnPortal, WaveSpawner,
+};
+use crate::player::{Experience, Player};
+use crate::state::GameState;
+use crate::world::{ArenaSize, WorldBounds};
+
+/// Where the current run is saved, relative to the working directory.
+const SAVE_PATH: &str = "savegame.json";
+
+/// Key that saves the current run.
+const SAVE_KEY: KeyCode = KeyCode::F5;
+
+/// Snapshot of a run in progress, enough to pick it up again later.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SaveData {
+    pub player: PlayerSave,
+    pub score: u32,
+    /// Waves already sent, so the run continues with the next one.
+    pub wave: u32,
+    /// Whether the boss already showed up, so beating it isn't redone.
+    #[serde(default)]
+    pub boss_spawned: bool,
+    /// Seconds survived, so the difficulty picks up where it left off.
+    #[serde(default)]
+    pub elapsed: f32,
+    pub opponents: Vec<OpponentSave>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PlayerSave {
+    pub position: [f32; 2],
+    pub health: f32,
+    pub max_health: f32,
+    pub attack_damage: f32,
+    pub xp: f32,
+    pub level: u32,
+    pub xp_to_next: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OpponentSave {
+    /// Missing from saves made before there were enemy types.
+    #[serde(default)]
+    pub kind: EnemyType,
+    #[serde(default)]
+    pub boss: bool,
+    /// Phase the boss was in, left at the first one for everyone else.
+    #[serde(default)]
+    pub phase: BossPhase,
+    pub position: [f32; 2],
+    pub health: f32,
+    /// Patrol waypoints, empty for an opponent without a route.
+    #[serde(default)]
+    pub patrol: Vec<[f32; 2]>,
+}
+
+/// Ask for the current run to be saved, from a key or the pause menu.
+#[derive(Event)]
+pub struct SaveRequested;
+
+/// Save read at startup, applied to the first run once it has spawned.
+#[derive(Resource)]
+struct PendingLoad(SaveData);
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        // Read right away, so a broken save is reported before the menu shows
+        if let Some(save) = load_game(SAVE_PATH) {
+            info!("Found a saved run, it continues when the game starts");
+            app.insert_resource(PendingLoad(save));
+        }
+
+        app.add_event::<SaveRequested>()
+            // Before the fixed steps, so nothing moves with the fresh run's
+            // state once the save has been applied
+            .add_systems(
+                PreUpdate,
+                apply_pending_load
+                    .run_if(in_state(GameState::Playing).and_then(resource_exists::<PendingLoad>)),
+            )
+            .add_systems(
+                Update,
+                (request_save_key, save_current_run)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// Write `save` to `path` as JSON. Failing to save is only worth a warning.
+pub fn save_game(path: &str, save: &SaveData) {
+    let text = match serde_json::to_string_pretty(save) {
+        Ok(text) => text,
+        Err(err) => {
+            warn!("Couldn't serialize the save: {err}");
+            return;
+        }
+    };
+
+    match fs::write(path, text) {
+        Ok(()) => info!("Saved the run to {path}"),
+        Err(err) => warn!("Couldn't save the run to {path}: {err}"),
+    }
+}
+
+/// Read a save from `path`. A missing or corrupt file gives `None`, and the
+/// game simply starts fresh.
+pub fn load_game(path: &str) -> Option<SaveData> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) => {
+            info!("No save loaded from {path} ({err}), starting fresh");
+            return None;
+        }
+    };
+
+    match serde_json::from_str(&text) {
+        Ok(save) => Some(save),
+        Err(err) => {
+            warn!("Invalid save in {path}, starting fresh: {err}");
+            None
+        }
+    }
+}
+
+fn request_save_key(kb_input: Res<ButtonInput<KeyCode>>, mut requests: EventWriter<SaveRequested>) {
+    if kb_input.just_pressed(SAVE_KEY) {
+        requests.send(SaveRequested);
+    }
+}
+
+/// Snapshot the run and write it to disk whenever a save is requested.
+///
+/// Runs while paused as well, so the pause menu can save. Opponents that are
+/// already dying count as defeated and are left out. Ones still in a spawn
+/// portal are saved as already through it, at full health.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn save_current_run(
+    mut requests: EventReader<SaveRequested>,
+    player: Query<(&Transform, &Health, &Experience, &AttackDamage), With<Player>>,
+    opponents: Query<
+        (&EnemyType, &Transform, &Health, Option<&Boss>, Option<&Patrol>),
+        (With<Opponent>, Without<Dying>),
+    >,
+    portals: Query<(&SpawnPortal, &Transform)>,
+    score: Res<Score>,
+    spawner: Res<WaveSpawner>,
+    fight: Res<BossFight>,
+    difficulty: Res<Difficulty>,
+    config: Res<GameConfig>,
+) {
+    if requests.read().count() == 0 {
+        return;
+    }
+
+    let Ok((transform, health, experience, damage)) = player.get_single() else {
+        return;
+    };
+
+    let save = SaveData {
+        player: PlayerSave {
+            position: transform.translation.truncate().to_array(),
+            health: health.current,
+            max_health: health.max,
+            attack_damage: damage.0,
+            xp: experience.current,
+            level: experience.level,
+            xp_to_next: experience.to_next,
+        },
+        score: score.0,
+        wave: spawner.wave,
+        boss_spawned: fight.spawned,
+        elapsed: difficulty.elapsed.elapsed_secs(),
+        opponents: opponents
+            .iter()
+            .map(|(kind, transform, health, boss, patrol)| OpponentSave {
+                kind: *kind,
+                boss: boss.is_some(),
+                phase: boss.map(|boss| boss.phase).unwrap_or_default(),
+                position: transform.translation.truncate().to_array(),
+                health: health.current,
+                patrol: patrol
+                    .map(|patrol| patrol.waypoints.iter().map(|point| point.to_array()).collect())
+                    .unwrap_or_default(),
+            })
+            .chain(portals.iter().map(|(portal, transform)| OpponentSave {
+                kind: portal.kind,
+                boss: false,
+                phase: BossPhase::default(),
+                position: transform.translation.truncate().to_array(),
+                health: portal.kind.max_health(&config),
+                patrol: Vec::new(),
+            }))
+            .collect(),
+    };
+
+    save_game(SAVE_PATH, &save);
+}
+
+/// Replace the freshly spawned run with the saved one.
+///
+/// Waits for the player to exist, since the run is spawned through commands
+/// on entering the playing state.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn apply_pending_load(
+    mut commands: Commands,
+    pending: Res<PendingLoad>,
+    mut player: Query<
+        (&mut Transform, &mut Health, &mut Experience, &mut AttackDamage),
+        With<Player>,
+    >,
+    opponents: Query<Entity, With<Opponent>>,
+    mut score: ResMut<Score>,
+    mut spawner: ResMut<WaveSpawner>,
+    mut fight: ResMut<BossFight>,
+    mut difficulty: ResMut<Difficulty>,
+    sprites: Res<OpponentSprites>,
+    config: Res<GameConfig>,
+    bounds: Res<WorldBounds>,
+    mut arena: ResMut<ArenaSize>,
+) {
+    let Ok((mut transform, mut health, mut experience, mut damage)) = player.get_single_mut()
+    else {
+        return;
+    };
+
+    let save = &pending.0;
+
+    transform.translation = Vec2::from_array(save.player.position).extend(transform.translation.z);
+    health.max = save.player.max_health;
+    health.current = save.player.health.min(health.max);
+    damage.0 = save.player.attack_damage;
+    experience.current = save.player.xp;
+    experience.level = save.player.level;
+    experience.to_next = save.player.xp_to_next;
+
+    score.0 = save.score;
+    spawner.wave = save.wave;
+    fight.spawned = save.boss_spawned;
+    // scale_difficulty works out the tier from it on the next frame
+    difficulty
+        .elapsed
+        .set_elapsed(Duration::from_secs_f32(save.elapsed.max(0.)));
+
+    for entity in &opponents {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    for opponent in &save.opponents {
+        let position = Vec2::from_array(opponent.position);
+
+        // Straight back into its saved phase, without the stun and the
+        // BossPhaseChanged of moving into it
+        if opponent.boss {
+            if let Some(size) = opponent.phase.arena_size() {
+                arena.set(size);
+            }
+
+            let entity = spawn_boss(&mut commands, &config, &sprites, &bounds, position);
+            let max = boss_max_health(&config);
+            commands.entity(entity).insert((
+                Boss::new(opponent.phase),
+                Health {
+                    current: opponent.health.min(max),
+                    max,
+                },
+            ));
+            continue;
+        }
+
+        let entity = spawn_opponent(
+            &mut commands,
+            &config,
+            &sprites,
+            &bounds,
+            opponent.kind,
+            position,
+        );
+        let max = opponent.kind.max_health(&config);
+        commands.entity(entity).insert(Health {
+            current: opponent.health.min(max),
+            max,
+        });
+
+        if !opponent.patrol.is_empty() {
+            let waypoints = opponent.patrol.iter().copied().map(Vec2::from_array).collect();
+            commands.entity(entity).insert(Patrol::new(waypoints));
+        }
+    }
+
+    info!(
+        "Continuing saved run at wave {} with {} opponents",
+        save.wave,
+        save.opponents.len()
+    );
+    commands.remove_resource::<PendingLoad>();
+}
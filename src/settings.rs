@@ -0,0 +1,211 @@
+use std::fs;
+
+use bevy::audio::Volume;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::camera::{CAM_LERP_FACTOR, ZOOM_SPEED};
+
+/// Where the player's settings are kept between runs, relative to the
+/// working directory.
+const SETTINGS_PATH: &str = "settings.ron";
+
+/// Default master volume, from 0 (muted) to 1 (full).
+const MASTER_VOLUME: f32 = 1.;
+
+/// Player preferences changed from the settings screen and saved to disk.
+///
+/// Unlike `GameConfig` these are meant to be changed while the game runs, so
+/// systems should read them every frame rather than once at spawn.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Settings {
+    pub master_volume: f32,
+    pub cam_lerp_factor: f32,
+    pub zoom_sensitivity: f32,
+    /// Seed for every run's `GameRng`, or a fresh random one per run when
+    /// unset. Every run logs its seed, so setting this repeats it exactly.
+    pub seed: Option<u64>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            master_volume: MASTER_VOLUME,
+            cam_lerp_factor: CAM_LERP_FACTOR,
+            zoom_sensitivity: ZOOM_SPEED,
+            seed: None,
+        }
+    }
+}
+
+/// A single adjustable value in `Settings`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettingKind {
+    MasterVolume,
+    CameraLerp,
+    ZoomSensitivity,
+}
+
+impl SettingKind {
+    /// Every setting, in the order shown on the settings screen.
+    pub const ALL: [Self; 3] = [Self::MasterVolume, Self::CameraLerp, Self::ZoomSensitivity];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::MasterVolume => "Master volume",
+            Self::CameraLerp => "Camera follow speed",
+            Self::ZoomSensitivity => "Zoom sensitivity",
+        }
+    }
+
+    /// Amount a single press of the + or - button changes the value by.
+    fn step(self) -> f32 {
+        match self {
+            Self::MasterVolume => 0.1,
+            Self::CameraLerp => 0.5,
+            Self::ZoomSensitivity => 0.02,
+        }
+    }
+
+    /// Smallest and largest allowed values.
+    fn range(self) -> (f32, f32) {
+        match self {
+            Self::MasterVolume => (0., 1.),
+            Self::CameraLerp => (0.5, 10.),
+            Self::ZoomSensitivity => (0.02, 0.5),
+        }
+    }
+}
+
+impl Settings {
+    pub fn value(&self, kind: SettingKind) -> f32 {
+        match kind {
+            SettingKind::MasterVolume => self.master_volume,
+            SettingKind::CameraLerp => self.cam_lerp_factor,
+            SettingKind::ZoomSensitivity => self.zoom_sensitivity,
+        }
+    }
+
+    /// Move a setting by `steps` of its step size, staying within its range.
+    pub fn adjust(&mut self, kind: SettingKind, steps: f32) {
+        let value = self.value(kind) + steps * kind.step();
+        self.set(kind, value);
+    }
+
+    fn set(&mut self, kind: SettingKind, value: f32) {
+        let (min, max) = kind.range();
+        // Rounding to the step keeps repeated presses from drifting off
+        // round numbers through float error
+        let value = ((value / kind.step()).round() * kind.step()).clamp(min, max);
+
+        match kind {
+            SettingKind::MasterVolume => self.master_volume = value,
+            SettingKind::CameraLerp => self.cam_lerp_factor = value,
+            SettingKind::ZoomSensitivity => self.zoom_sensitivity = value,
+        }
+    }
+
+    /// Read the settings from `path`, falling back to the defaults if the file
+    /// is missing or can't be parsed.
+    pub fn load(path: &str) -> Self {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                info!("No settings loaded from {path} ({err}), using defaults");
+                return Self::default();
+            }
+        };
+
+        match ron::from_str::<Self>(&text) {
+            Ok(settings) => settings.validated(),
+            Err(err) => {
+                warn!("Invalid settings in {path}, using defaults: {err}");
+                Self::default()
+            }
+        }
+    }
+
+    /// Write the settings to `path`. Failing to save is only worth a warning,
+    /// the game carries on with the values in memory.
+    pub fn save(&self, path: &str) {
+        let text = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(text) => text,
+            Err(err) => {
+                warn!("Couldn't serialize settings: {err}");
+                return;
+            }
+        };
+
+        if let Err(err) = fs::write(path, text) {
+            warn!("Couldn't save settings to {path}: {err}");
+        }
+    }
+
+    /// Bring every value back into its allowed range, using the default for
+    /// anything that isn't a number.
+    fn validated(self) -> Self {
+        let defaults = Self::default();
+        let mut settings = Self {
+            seed: self.seed,
+            ..defaults.clone()
+        };
+
+        for kind in SettingKind::ALL {
+            let value = self.value(kind);
+            let value = if value.is_finite() {
+                value
+            } else {
+                warn!("Setting {} must be a number, got {value}", kind.label());
+                defaults.value(kind)
+            };
+            settings.set(kind, value);
+        }
+
+        settings
+    }
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        // Loaded right away like `GameConfig`, so the first frame already
+        // uses the saved values
+        let settings = Settings::load(SETTINGS_PATH);
+        info!("Loaded {settings:?}");
+
+        app.insert_resource(settings).add_systems(
+            Update,
+            (apply_volume, save_settings).run_if(resource_changed::<Settings>),
+        );
+    }
+}
+
+/// Scale every sound by the master volume.
+///
+/// The global volume is only picked up when a sound starts, so the ones
+/// already playing are turned up or down here as well.
+fn apply_volume(
+    settings: Res<Settings>,
+    mut volume: ResMut<GlobalVolume>,
+    sinks: Query<(&AudioSink, Option<&PlaybackSettings>)>,
+) {
+    volume.volume = Volume::new(settings.master_volume);
+
+    for (sink, playback) in &sinks {
+        let own_volume = playback.map_or(1., |playback| playback.volume.get());
+        sink.set_volume(settings.master_volume * own_volume);
+    }
+}
+
+/// Write the settings back to disk whenever they change.
+fn save_settings(settings: Res<Settings>) {
+    // Inserting the loaded settings counts as a change, but there is nothing
+    // new to write yet
+    if settings.is_added() {
+        return;
+    }
+
+    settings.save(SETTINGS_PATH);
+}
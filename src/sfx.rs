@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+
+use crate::combat::{AttackSwung, OpponentHit};
+
+/// Sound played whenever the player swings, relative to the assets folder.
+const SWING_SOUND: &str = "audio/swing.ogg";
+
+/// Sound played whenever an opponent takes damage, relative to the assets folder.
+const HIT_SOUND: &str = "audio/hit.ogg";
+
+/// Sound effects loaded once at startup.
+#[derive(Resource)]
+pub struct SfxHandles {
+    pub swing: Handle<AudioSource>,
+    pub hit: Handle<AudioSource>,
+}
+
+pub struct SfxPlugin;
+
+impl Plugin for SfxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_sfx)
+            .add_systems(Update, (play_swing_sfx, play_hit_sfx));
+    }
+}
+
+fn load_sfx(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SfxHandles {
+        swing: asset_server.load(SWING_SOUND),
+        hit: asset_server.load(HIT_SOUND),
+    });
+}
+
+/// Play a sound as its own entity, so overlapping sounds don't cut each other off.
+fn play(commands: &mut Commands, source: &Handle<AudioSource>) {
+    commands.spawn(AudioBundle {
+        source: source.clone(),
+        settings: PlaybackSettings::DESPAWN,
+    });
+}
+
+fn play_swing_sfx(
+    mut commands: Commands,
+    mut swings: EventReader<AttackSwung>,
+    sfx: Res<SfxHandles>,
+) {
+    for _ in swings.read() {
+        play(&mut commands, &sfx.swing);
+    }
+}
+
+fn play_hit_sfx(mut commands: Commands, mut hits: EventReader<OpponentHit>, sfx: Res<SfxHandles>) {
+    for _ in hits.read() {
+        play(&mut commands, &sfx.hit);
+    }
+}
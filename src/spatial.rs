@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+use crate::components::{Collidable, COLLISION_RADIUS};
+use crate::pickup::Pickup;
+
+/// Buckets collidables into square cells so collision checks only have to
+/// compare bodies that share a cell, instead of every pair in the world.
+///
+/// Bodies are added to every cell their bounding box touches, so large
+/// obstacles still meet everything they overlap whatever the cell size.
+#[derive(Resource)]
+pub struct SpatialGrid {
+    pub cell_size: f32,
+    cells: HashMap<IVec2, Vec<Entity>>,
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self {
+            cell_size: COLLISION_RADIUS * 2.,
+            cells: HashMap::default(),
+        }
+    }
+}
+
+impl SpatialGrid {
+    fn cell(&self, position: Vec2) -> IVec2 {
+        (position / self.cell_size).floor().as_ivec2()
+    }
+
+    pub fn clear(&mut self) {
+        // Keep the allocations around, the same cells are usually reused next frame
+        for entities in self.cells.values_mut() {
+            entities.clear();
+        }
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Vec2, radius: f32) {
+        let min = self.cell(position - Vec2::splat(radius));
+        let max = self.cell(position + Vec2::splat(radius));
+
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                self.cells.entry(IVec2::new(x, y)).or_default().push(entity);
+            }
+        }
+    }
+
+    /// Every pair of entities sharing at least one cell, each reported once
+    /// and in a stable order.
+    pub fn candidate_pairs(&self) -> Vec<(Entity, Entity)> {
+        let mut pairs = HashSet::new();
+
+        for entities in self.cells.values() {
+            for (i, &a) in entities.iter().enumerate() {
+                for &b in &entities[i + 1..] {
+                    pairs.insert((a.min(b), a.max(b)));
+                }
+            }
+        }
+
+        let mut pairs: Vec<_> = pairs.into_iter().collect();
+        pairs.sort_unstable();
+        pairs
+    }
+}
+
+/// Rebuild the grid from the current collidable positions.
+///
+/// Pickups are left out, they are collected by their own overlap check.
+pub fn update_spatial_grid(
+    mut grid: ResMut<SpatialGrid>,
+    bodies: Query<(Entity, &Transform, &Collidable), Without<Pickup>>,
+) {
+    grid.clear();
+
+    for (entity, transform, collidable) in &bodies {
+        grid.insert(entity, transform.translation.truncate(), collidable.radius);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    #[test]
+    fn candidate_pairs_keep_overlaps_and_skip_distant_bodies() {
+        const BODIES: u32 = 500;
+        const ARENA: f32 = 3000.;
+
+        let mut rng = ChaCha8Rng::seed_from_u64(28);
+        let bodies: Vec<_> = (0..BODIES)
+            .map(|i| {
+                let position = Vec2::new(rng.gen_range(0.0..ARENA), rng.gen_range(0.0..ARENA));
+                (Entity::from_raw(i), position, COLLISION_RADIUS)
+            })
+            .collect();
+
+        let mut grid = SpatialGrid::default();
+        for &(entity, position, radius) in &bodies {
+            grid.insert(entity, position, radius);
+        }
+        let pairs = grid.candidate_pairs();
+
+        let all_pairs = (BODIES * (BODIES - 1) / 2) as usize;
+        assert!(pairs.len() < all_pairs / 20, "{} candidate pairs", pairs.len());
+
+        for (i, &(a, a_position, a_radius)) in bodies.iter().enumerate() {
+            for &(b, b_position, b_radius) in &bodies[i + 1..] {
+                if a_position.distance(b_position) <= a_radius + b_radius {
+                    assert!(pairs.binary_search(&(a.min(b), a.max(b))).is_ok());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use std::ops::Deref;
+use std::time::Duration;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::config::GameConfig;
+
+/// Top level flow of the game.
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameState {
+    #[default]
+    Menu,
+    Settings,
+    Playing,
+    GameOver,
+    Victory,
+    /// Level editor for placing obstacles, entered from the menu.
+    Editor,
+}
+
+/// Whether gameplay is currently running or paused, only exists while playing.
+#[derive(SubStates, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[source(GameState = GameState::Playing)]
+pub enum PauseState {
+    #[default]
+    Running,
+    Paused,
+}
+
+/// Fixed steps of simulation per second for movement and collisions.
+const PHYSICS_HZ: f64 = 60.;
+
+/// Default longest frame delta in seconds, see `GameConfig`.
+pub const MAX_FRAME_DELTA: f32 = 0.05;
+
+/// Brief global freeze on a big hit, for impact.
+///
+/// Unlike the player's time slow this leaves `Time` alone. The fixed step
+/// sets, and with them all movement, simply don't run until it is over, and
+/// the fixed time that piles up meanwhile is dropped so nothing jumps ahead
+/// afterwards. Input is still read, so a dash pressed during the freeze goes
+/// off on the first step after it.
+#[derive(Resource)]
+pub struct HitStop {
+    pub timer: Timer,
+}
+
+impl Default for HitStop {
+    /// Not frozen.
+    fn default() -> Self {
+        let mut timer = Timer::from_seconds(0., TimerMode::Once);
+        timer.tick(timer.duration());
+
+        Self { timer }
+    }
+}
+
+impl HitStop {
+    /// Freeze for `seconds`, unless an earlier freeze still lasts longer.
+    pub fn start(&mut self, seconds: f32) {
+        if self.timer.remaining_secs() < seconds {
+            self.timer = Timer::from_seconds(seconds, TimerMode::Once);
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.timer.finished()
+    }
+}
+
+/// Time as gameplay sees it, standing still outside of a run, while paused
+/// and during a hit-stop.
+///
+/// Reads the schedule's own `Time`, so it follows fixed time in the fixed
+/// step sets and virtual time everywhere else.
+#[derive(SystemParam)]
+pub struct GameClock<'w> {
+    time: Res<'w, Time>,
+    state: Res<'w, State<GameState>>,
+    pause: Option<Res<'w, State<PauseState>>>,
+    hit_stop: Res<'w, HitStop>,
+}
+
+impl GameClock<'_> {
+    pub fn is_running(&self) -> bool {
+        *self.state.get() == GameState::Playing
+            && self
+                .pause
+                .as_ref()
+                .is_some_and(|pause| *pause.get() == PauseState::Running)
+            && !self.hit_stop.is_active()
+    }
+
+    /// Time passed since the last update, zero whenever gameplay is stopped.
+    pub fn delta(&self) -> Duration {
+        if self.is_running() {
+            self.time.delta()
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+/// A `Timer` that only advances with a `GameClock`.
+///
+/// Gameplay cooldowns use it, so they freeze with the game however the
+/// system ticking them is scheduled. It reads like a plain `Timer`, but can
+/// only be moved along through the clock, `reset` or `finish`.
+#[derive(Debug, Clone)]
+pub struct GameTimer(Timer);
+
+impl GameTimer {
+    pub fn from_seconds(seconds: f32, mode: TimerMode) -> Self {
+        Self(Timer::from_seconds(seconds, mode))
+    }
+
+    /// Create a timer that has already run out, ready straight away.
+    pub fn new_finished(seconds: f32, mode: TimerMode) -> Self {
+        let mut timer = Self::from_seconds(seconds, mode);
+        timer.finish();
+        timer
+    }
+
+    pub fn tick(&mut self, clock: &GameClock) -> &Self {
+        self.0.tick(clock.delta());
+        self
+    }
+
+    /// Advance `scale` times as fast as the clock.
+    pub fn tick_scaled(&mut self, clock: &GameClock, scale: f32) -> &Self {
+        self.0.tick(clock.delta().mul_f32(scale));
+        self
+    }
+
+    pub fn reset(&mut self) {
+        self.0.reset();
+    }
+
+    /// Skip to the end, as if the rest of the time had passed.
+    pub fn finish(&mut self) {
+        let remaining = self.0.remaining();
+        self.0.tick(remaining);
+    }
+}
+
+impl Deref for GameTimer {
+    type Target = Timer;
+
+    fn deref(&self) -> &Timer {
+        &self.0
+    }
+}
+
+/// Run condition for anything that should stand still during a hit-stop.
+pub fn not_hit_stopped(hit_stop: Res<HitStop>) -> bool {
+    !hit_stop.is_active()
+}
+
+/// Ordered stages of a gameplay frame, shared by every plugin.
+///
+/// All of them only run while the game is playing and not paused.
+///
+/// Movement, Ai and Physics run in `FixedUpdate`, so collisions behave the
+/// same whatever the frame rate; `Time` in those systems is the fixed delta.
+/// They can run zero or several times per frame, so they should never read
+/// `just_pressed` input directly, see `PlayerInput`. They are also held back
+/// during a `HitStop`. The remaining sets run once per frame in `Update`,
+/// after that frame's fixed steps.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GameSet {
+    /// Player driven movement.
+    Movement,
+    /// Opponent decisions and movement.
+    Ai,
+    /// Knockback and collision resolution.
+    Physics,
+    /// Attacks, projectiles and damage.
+    Combat,
+    /// Removing the dead and reacting to deaths.
+    Cleanup,
+    Camera,
+    Ui,
+}
+
+pub struct StatePlugin;
+
+impl Plugin for StatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<GameState>()
+            .add_sub_state::<PauseState>()
+            // Gameplay entities are scoped to Playing so a retry starts clean
+            .enable_state_scoped_entities::<GameState>()
+            .insert_resource(Time::<Fixed>::from_hz(PHYSICS_HZ))
+            .init_resource::<HitStop>()
+            .add_systems(Startup, limit_frame_delta)
+            .add_systems(OnEnter(GameState::Playing), reset_hit_stop)
+            .configure_sets(
+                FixedUpdate,
+                (GameSet::Movement, GameSet::Ai, GameSet::Physics)
+                    .chain()
+                    .run_if(in_state(PauseState::Running).and_then(not_hit_stopped)),
+            )
+            .configure_sets(
+                Update,
+                (
+                    GameSet::Combat,
+                    GameSet::Cleanup,
+                    GameSet::Camera,
+                    GameSet::Ui,
+                )
+                    .chain()
+                    .run_if(in_state(PauseState::Running)),
+            )
+            .add_systems(OnEnter(PauseState::Paused), pause_time)
+            .add_systems(OnExit(PauseState::Paused), resume_time)
+            .add_systems(Update, toggle_pause.run_if(in_state(GameState::Playing)))
+            // After the big hits of the frame, so a freeze started this frame
+            // already counts down from the next
+            .add_systems(
+                Update,
+                tick_hit_stop
+                    .after(GameSet::Cleanup)
+                    .run_if(in_state(PauseState::Running)),
+            );
+    }
+}
+
+/// Cap how much virtual time a single frame can advance.
+///
+/// After a stall, like a window drag or loading, the next frame would
+/// otherwise see the whole gap at once. Fixed steps would then run many times
+/// in a row to catch up, and per frame movement like projectiles would jump
+/// far enough to skip past whatever it should hit. Normal frames are well
+/// under the cap and are unaffected; the game simply runs slower than real
+/// time during a spike.
+fn limit_frame_delta(mut time: ResMut<Time<Virtual>>, config: Res<GameConfig>) {
+    time.set_max_delta(Duration::from_secs_f32(config.max_frame_delta));
+}
+
+fn reset_hit_stop(mut hit_stop: ResMut<HitStop>) {
+    *hit_stop = HitStop::default();
+}
+
+/// Count a hit-stop down in real time, throwing away the fixed time that
+/// built up over this frame while it lasts.
+fn tick_hit_stop(
+    mut hit_stop: ResMut<HitStop>,
+    mut fixed: ResMut<Time<Fixed>>,
+    real_time: Res<Time<Real>>,
+) {
+    if !hit_stop.is_active() {
+        return;
+    }
+
+    let overstep = fixed.overstep();
+    fixed.discard_overstep(overstep);
+    hit_stop.timer.tick(real_time.delta());
+}
+
+/// Toggle between running and paused with escape.
+fn toggle_pause(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    state: Res<State<PauseState>>,
+    mut next_state: ResMut<NextState<PauseState>>,
+) {
+    if !kb_input.just_pressed(KeyCode::Escape) {
+        return;
+    }
+
+    next_state.set(match state.get() {
+        PauseState::Running => PauseState::Paused,
+        PauseState::Paused => PauseState::Running,
+    });
+}
+
+/// Stop virtual time so nothing driven by it advances while paused.
+///
+/// Gameplay systems don't run while paused either, so on resume the first
+/// frame only sees its own delta instead of the whole pause.
+fn pause_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+fn resume_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use bevy::state::app::StatesPlugin;
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+
+    /// Headless app already in a running `GameState::Playing`, with every
+    /// update advancing time by `step`.
+    pub(crate) fn playing_app(step: Duration) -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin, StatePlugin))
+            .init_resource::<GameConfig>()
+            .init_resource::<ButtonInput<KeyCode>>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(step));
+
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
+        app.update();
+        app
+    }
+
+    #[derive(Resource)]
+    struct TestTimer(GameTimer);
+
+    /// Ticks outside of any `GameSet`, so only the clock can hold it back.
+    fn tick_test_timer(mut timer: ResMut<TestTimer>, clock: GameClock) {
+        timer.0.tick(&clock);
+    }
+
+    fn timer_app() -> App {
+        let mut app = playing_app(Duration::from_millis(10));
+        app.insert_resource(TestTimer(GameTimer::from_seconds(60., TimerMode::Once)))
+            .add_systems(Update, tick_test_timer);
+        app
+    }
+
+    fn elapsed(app: &App) -> Duration {
+        app.world().resource::<TestTimer>().0.elapsed()
+    }
+
+    #[test]
+    fn game_timer_advances_while_running() {
+        let mut app = timer_app();
+        let before = elapsed(&app);
+
+        for _ in 0..5 {
+            app.update();
+        }
+
+        assert!(elapsed(&app) > before);
+    }
+
+    #[test]
+    fn game_timer_stands_still_while_paused() {
+        let mut app = timer_app();
+        app.world_mut()
+            .resource_mut::<NextState<PauseState>>()
+            .set(PauseState::Paused);
+        app.update();
+        let before = elapsed(&app);
+
+        for _ in 0..5 {
+            app.update();
+        }
+
+        assert_eq!(elapsed(&app), before);
+    }
+}
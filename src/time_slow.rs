@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+
+use crate::player::KeyBindings;
+use crate::state::{GameState, PauseState};
+
+/// Speed of virtual time while the slow is active, relative to real time.
+const TIME_SLOW_SPEED: f32 = 0.4;
+
+/// Real seconds a time slow lasts.
+const TIME_SLOW_DURATION: f32 = 3.;
+
+/// Real seconds between the start of one time slow and the next.
+const TIME_SLOW_COOLDOWN: f32 = 15.;
+
+/// The player's bullet time ability, slowing down everything driven by
+/// virtual time.
+///
+/// Both timers run on real time, so the slow lasts the same however slow it
+/// makes the game. Camera systems use real time as well and stay responsive
+/// while it is active.
+#[derive(Resource)]
+pub struct TimeSlow {
+    pub timer: Timer,
+    pub cooldown: Timer,
+}
+
+impl Default for TimeSlow {
+    /// Not running and ready to use straight away.
+    fn default() -> Self {
+        let mut timer = Timer::from_seconds(TIME_SLOW_DURATION, TimerMode::Once);
+        timer.tick(timer.duration());
+        let mut cooldown = Timer::from_seconds(TIME_SLOW_COOLDOWN, TimerMode::Once);
+        cooldown.tick(cooldown.duration());
+
+        Self { timer, cooldown }
+    }
+}
+
+impl TimeSlow {
+    pub fn is_active(&self) -> bool {
+        !self.timer.finished()
+    }
+}
+
+pub struct TimeSlowPlugin;
+
+impl Plugin for TimeSlowPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TimeSlow>()
+            .add_systems(
+                Update,
+                (tick_time_slow, start_time_slow)
+                    .chain()
+                    .run_if(in_state(PauseState::Running)),
+            )
+            // Pausing stops the slow early, so resuming is back at full speed
+            .add_systems(OnEnter(PauseState::Paused), end_time_slow)
+            .add_systems(OnExit(GameState::Playing), reset_time_slow);
+    }
+}
+
+/// Slow time down when the key is pressed and the ability is ready.
+fn start_time_slow(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut slow: ResMut<TimeSlow>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    if !kb_input.just_pressed(bindings.time_slow) || !slow.cooldown.finished() {
+        return;
+    }
+
+    slow.timer.reset();
+    slow.cooldown.reset();
+    time.set_relative_speed(TIME_SLOW_SPEED);
+}
+
+/// Count the slow and its cooldown down in real time, restoring full speed
+/// once the slow runs out.
+fn tick_time_slow(
+    mut slow: ResMut<TimeSlow>,
+    mut time: ResMut<Time<Virtual>>,
+    real_time: Res<Time<Real>>,
+) {
+    slow.cooldown.tick(real_time.delta());
+
+    if slow.is_active() && slow.timer.tick(real_time.delta()).just_finished() {
+        time.set_relative_speed(1.);
+    }
+}
+
+/// Stop a running slow right away, keeping its cooldown.
+fn end_time_slow(mut slow: ResMut<TimeSlow>, mut time: ResMut<Time<Virtual>>) {
+    let duration = slow.timer.duration();
+    slow.timer.tick(duration);
+    time.set_relative_speed(1.);
+}
+
+/// Leave a run at full speed, with the ability ready for the next one.
+fn reset_time_slow(mut slow: ResMut<TimeSlow>, mut time: ResMut<Time<Virtual>>) {
+    *slow = TimeSlow::default();
+    time.set_relative_speed(1.);
+}
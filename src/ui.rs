@@ -0,0 +1,1522 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::sprite::Anchor;
+use bevy::ui::UiSystem;
+use bevy::utils::HashSet;
+
+use crate::boss::{BossPhase, BossPhaseChanged};
+use crate::camera::WorldCamera;
+use crate::combat::{AttackCooldown, Combo, CombatEvent, Dying, Score, UltimateCharge};
+use crate::components::{Collidable, Health, Stamina};
+use crate::difficulty::Difficulty;
+use crate::opponent::Opponent;
+use crate::player::{Experience, KeyBindings, LevelUp, Player};
+use crate::save::SaveRequested;
+use crate::settings::{SettingKind, Settings};
+use crate::state::{GameSet, GameState, PauseState};
+use crate::world::WorldBounds;
+
+/// Size of the player's health bar in pixels.
+const HEALTH_BAR_SIZE: Vec2 = Vec2::new(200., 20.);
+
+/// Height of the stamina bar under the health bar, in pixels.
+const STAMINA_BAR_HEIGHT: f32 = 10.;
+
+/// Height of the attack cooldown bar under the stamina bar, in pixels.
+const COOLDOWN_BAR_HEIGHT: f32 = 6.;
+
+/// Height of the ultimate charge bar under the attack cooldown bar, in pixels.
+const ULTIMATE_BAR_HEIGHT: f32 = 8.;
+
+/// Health fraction below which the health bar turns red.
+const LOW_HEALTH_FRACTION: f32 = 0.25;
+
+/// Health fraction below which the red vignette starts showing, getting
+/// stronger the lower health goes.
+const VIGNETTE_HEALTH_FRACTION: f32 = 0.4;
+
+/// Strongest the vignette gets, as an alpha from 0 to 1.
+const VIGNETTE_MAX_ALPHA: f32 = 0.8;
+
+/// Speed of the vignette's pulse, in radians per second.
+const VIGNETTE_PULSE_SPEED: f32 = 6.;
+
+/// Side length of the generated vignette texture, in pixels. It is stretched
+/// over the whole screen, so it doesn't need to be large.
+const VIGNETTE_TEXTURE_SIZE: u32 = 64;
+
+/// Size of the bars floating above opponents, in world units.
+const ENEMY_HEALTH_BAR_SIZE: Vec2 = Vec2::new(40., 5.);
+
+/// Gap between the top of an opponent and its health bar.
+const ENEMY_HEALTH_BAR_GAP: f32 = 8.;
+
+/// Size of the minimap panel in the bottom right corner, in pixels.
+const MINIMAP_SIZE: Vec2 = Vec2::new(200., 140.);
+
+/// Diameter of the dots on the minimap, in pixels.
+const MINIMAP_DOT_SIZE: f32 = 6.;
+
+/// Most off-screen opponents pointed at at once, the ones nearest the player.
+const MAX_OFFSCREEN_INDICATORS: usize = 6;
+
+/// Distance between an off-screen indicator and the window edge, in pixels.
+const INDICATOR_MARGIN: f32 = 24.;
+
+/// Length of an off-screen indicator arrow, in pixels.
+const INDICATOR_LENGTH: f32 = 20.;
+
+/// Distance from the player at which off-screen indicators are faintest.
+const INDICATOR_FADE_DISTANCE: f32 = 1500.;
+
+/// Opacity of the off-screen indicators for the furthest opponents.
+const INDICATOR_MIN_ALPHA: f32 = 0.2;
+
+/// Lines kept in the combat log before the oldest ones drop off.
+const COMBAT_LOG_LINES: usize = 6;
+
+/// Resting color of every menu button.
+const BUTTON_COLOR: Color = Color::srgb(0.25, 0.25, 0.35);
+
+/// Color of a menu button under the cursor.
+const BUTTON_HOVERED_COLOR: Color = Color::srgb(0.35, 0.35, 0.5);
+
+/// Color of a menu button while it is held down.
+const BUTTON_PRESSED_COLOR: Color = Color::srgb(0.45, 0.6, 0.45);
+
+/// Color of the menu button focused for keyboard and gamepad navigation.
+const BUTTON_FOCUSED_COLOR: Color = Color::srgb(0.4, 0.4, 0.6);
+
+/// The filled part of the player's health bar.
+#[derive(Component)]
+struct HealthBar;
+
+/// Health bar floating above an opponent, pointing at its filled part.
+#[derive(Component)]
+struct EnemyHealthBar {
+    fill: Entity,
+}
+
+/// Red glow around the screen edges while the player's health is low.
+#[derive(Component)]
+struct Vignette;
+
+/// The filled part of the player's stamina bar.
+#[derive(Component)]
+struct StaminaBar;
+
+/// Overlay on the attack cooldown bar that shrinks as the cooldown runs out.
+#[derive(Component)]
+struct CooldownBar;
+
+/// The filled part of the ultimate charge bar.
+#[derive(Component)]
+struct UltimateBar;
+
+/// Label next to the ultimate charge bar, only shown once it is ready.
+#[derive(Component)]
+struct UltimateReadyText;
+
+#[derive(Component)]
+struct ScoreText;
+
+/// Text showing the player's level and progress towards the next one.
+#[derive(Component)]
+struct LevelText;
+
+/// Current difficulty tier, under the combo.
+#[derive(Component)]
+struct DifficultyText;
+
+/// Current combo under the level, empty while there is no combo going.
+#[derive(Component)]
+struct ComboText;
+
+/// Text showing the most recent combat log lines.
+#[derive(Component)]
+struct CombatLogText;
+
+/// Most recent combat events as text, oldest first.
+#[derive(Resource, Default)]
+struct CombatLog {
+    lines: VecDeque<String>,
+}
+
+/// Which button on the current screen has keyboard and gamepad focus.
+///
+/// Buttons are counted in reading order, top to bottom and then left to
+/// right, so every screen is navigated the way it is laid out.
+#[derive(Resource, Default)]
+struct MenuSelection {
+    index: usize,
+    /// The button at `index`, worked out again every frame.
+    focused: Option<Entity>,
+    /// Button activated from the keyboard or gamepad last frame.
+    pressed: Option<Entity>,
+}
+
+/// Panel the minimap dots are drawn on.
+#[derive(Component)]
+struct Minimap;
+
+/// Dot on the minimap following `target` around the world.
+#[derive(Component)]
+struct MinimapDot {
+    target: Entity,
+}
+
+/// Root of the overlay shown while the game is paused.
+#[derive(Component)]
+struct PauseOverlay;
+
+/// Button on the pause overlay that resumes the game.
+#[derive(Component)]
+struct ResumeButton;
+
+/// Button on the pause overlay that saves the run.
+#[derive(Component)]
+struct SaveButton;
+
+/// Button on the main menu that starts the game.
+#[derive(Component)]
+struct StartButton;
+
+/// Button on the game over and victory screens that starts a new run.
+#[derive(Component)]
+struct RetryButton;
+
+/// Button on the main menu that opens the settings screen.
+#[derive(Component)]
+struct SettingsButton;
+
+/// Button on the main menu that opens the level editor.
+#[derive(Component)]
+struct EditorButton;
+
+/// Button on the settings screen that goes back to the main menu.
+#[derive(Component)]
+struct BackButton;
+
+/// Button on the settings screen that puts every setting back to its default.
+#[derive(Component)]
+struct ResetSettingsButton;
+
+/// Button that moves `kind` by `steps` of its step size when clicked.
+#[derive(Component)]
+struct AdjustSettingButton {
+    kind: SettingKind,
+    steps: f32,
+}
+
+/// Text showing the current value of a setting.
+#[derive(Component)]
+struct SettingValueText(SettingKind);
+
+pub struct UiPlugin;
+
+impl Plugin for UiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CombatLog>()
+            .init_resource::<MenuSelection>()
+            .add_systems(OnEnter(GameState::Menu), setup_menu)
+            .add_systems(OnEnter(GameState::Settings), setup_settings_screen)
+            .add_systems(
+                OnEnter(GameState::Playing),
+                (
+                    setup_health_ui,
+                    setup_stamina_ui,
+                    setup_cooldown_ui,
+                    setup_ultimate_ui,
+                    setup_score_ui,
+                    setup_minimap,
+                    setup_combat_log,
+                    setup_vignette,
+                ),
+            )
+            .add_systems(OnEnter(GameState::GameOver), spawn_game_over_screen)
+            .add_systems(OnEnter(GameState::Victory), spawn_victory_screen)
+            .add_systems(OnEnter(PauseState::Paused), spawn_pause_overlay)
+            .add_systems(OnExit(PauseState::Paused), despawn_pause_overlay)
+            .add_systems(
+                Update,
+                (
+                    update_health_ui,
+                    low_health_vignette,
+                    update_stamina_ui,
+                    update_cooldown_ui,
+                    update_ultimate_ui,
+                    update_score_text,
+                    update_level_text,
+                    update_combo_text,
+                    update_difficulty_text,
+                    update_minimap,
+                    offscreen_indicators,
+                    combat_log_ui,
+                    (spawn_enemy_healthbars, update_enemy_healthbars).chain(),
+                )
+                    .in_set(GameSet::Ui),
+            )
+            // Right after the mouse is handled, so a button activated from the
+            // keyboard reaches the click handlers in the same frame
+            .add_systems(PreUpdate, navigate_menus.after(UiSystem::Focus))
+            .add_systems(Update, button_colors)
+            .add_systems(
+                Update,
+                (menu_button_system, settings_menu_button, editor_menu_button)
+                    .run_if(in_state(GameState::Menu)),
+            )
+            .add_systems(
+                Update,
+                (
+                    settings_buttons,
+                    update_setting_values.run_if(resource_changed::<Settings>),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Settings)),
+            )
+            .add_systems(
+                Update,
+                (resume_button, save_button).run_if(in_state(PauseState::Paused)),
+            )
+            .add_systems(
+                Update,
+                retry_button
+                    .run_if(in_state(GameState::GameOver).or_else(in_state(GameState::Victory))),
+            );
+    }
+}
+
+/// Spawn the title screen. It is scoped to the menu state, so leaving the menu
+/// cleans it up.
+fn setup_menu(mut commands: Commands) {
+    commands
+        .spawn((
+            StateScoped(GameState::Menu),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(40.),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Holy Combat",
+                TextStyle {
+                    font_size: 80.,
+                    ..default()
+                },
+            ));
+
+            spawn_button(parent, StartButton, "Start", 36.);
+
+            spawn_button(parent, SettingsButton, "Settings", 30.);
+            spawn_button(parent, EditorButton, "Level editor", 30.);
+        });
+}
+
+/// Spawn a menu button with a text label.
+fn spawn_button(parent: &mut ChildBuilder, marker: impl Component, label: &str, font_size: f32) {
+    parent
+        .spawn((
+            marker,
+            ButtonBundle {
+                style: Style {
+                    padding: UiRect::axes(Val::Px(24.), Val::Px(12.)),
+                    ..default()
+                },
+                background_color: BUTTON_COLOR.into(),
+                ..default()
+            },
+        ))
+        .with_children(|button| {
+            button.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size,
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// Spawn the settings screen with a row of - and + buttons for every setting.
+fn setup_settings_screen(mut commands: Commands, settings: Res<Settings>) {
+    commands
+        .spawn((
+            StateScoped(GameState::Settings),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Settings",
+                TextStyle {
+                    font_size: 60.,
+                    ..default()
+                },
+            ));
+
+            for kind in SettingKind::ALL {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            column_gap: Val::Px(16.),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn(TextBundle {
+                            text: Text::from_section(
+                                kind.label(),
+                                TextStyle {
+                                    font_size: 26.,
+                                    ..default()
+                                },
+                            ),
+                            style: Style {
+                                width: Val::Px(260.),
+                                ..default()
+                            },
+                            ..default()
+                        });
+
+                        spawn_button(row, AdjustSettingButton { kind, steps: -1. }, "-", 26.);
+
+                        row.spawn((
+                            SettingValueText(kind),
+                            TextBundle {
+                                text: Text::from_section(
+                                    format_setting(kind, settings.value(kind)),
+                                    TextStyle {
+                                        font_size: 26.,
+                                        ..default()
+                                    },
+                                ),
+                                style: Style {
+                                    width: Val::Px(80.),
+                                    ..default()
+                                },
+                                ..default()
+                            }
+                            .with_text_justify(JustifyText::Center),
+                        ));
+
+                        spawn_button(row, AdjustSettingButton { kind, steps: 1. }, "+", 26.);
+                    });
+            }
+
+            spawn_button(parent, ResetSettingsButton, "Reset to defaults", 26.);
+            spawn_button(parent, BackButton, "Back", 30.);
+        });
+}
+
+/// A setting's value as shown on the settings screen.
+fn format_setting(kind: SettingKind, value: f32) -> String {
+    match kind {
+        SettingKind::MasterVolume => format!("{:.0}%", value * 100.),
+        SettingKind::CameraLerp | SettingKind::ZoomSensitivity => format!("{value:.2}"),
+    }
+}
+
+/// Open the settings screen when its button on the main menu is clicked.
+fn settings_menu_button(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<SettingsButton>)>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for interaction in &buttons {
+        if *interaction == Interaction::Pressed {
+            next_state.set(GameState::Settings);
+        }
+    }
+}
+
+fn editor_menu_button(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<EditorButton>)>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for interaction in &buttons {
+        if *interaction == Interaction::Pressed {
+            next_state.set(GameState::Editor);
+        }
+    }
+}
+
+/// Handle every button on the settings screen. Changes apply right away.
+#[allow(clippy::type_complexity)]
+fn settings_buttons(
+    buttons: Query<
+        (
+            &Interaction,
+            Option<&AdjustSettingButton>,
+            Has<ResetSettingsButton>,
+            Has<BackButton>,
+        ),
+        Changed<Interaction>,
+    >,
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, adjust, reset, back) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        if let Some(adjust) = adjust {
+            settings.adjust(adjust.kind, adjust.steps);
+        } else if reset {
+            *settings = Settings::default();
+        } else if back {
+            next_state.set(GameState::Menu);
+        }
+    }
+}
+
+/// Refresh the values on the settings screen after they change.
+fn update_setting_values(
+    settings: Res<Settings>,
+    mut texts: Query<(&mut Text, &SettingValueText)>,
+) {
+    for (mut text, value) in &mut texts {
+        text.sections[0].value = format_setting(value.0, settings.value(value.0));
+    }
+}
+
+/// Start playing when the start button is clicked.
+fn menu_button_system(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<StartButton>)>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for interaction in &buttons {
+        if *interaction == Interaction::Pressed {
+            next_state.set(GameState::Playing);
+        }
+    }
+}
+
+/// Move the menu focus with the arrow keys, W and S or the d-pad, and
+/// activate the focused button with enter or the south face button.
+///
+/// Activating a button marks it as pressed, so the same handlers that react to
+/// clicks react to it, and lets go of it again on the next frame. Hovering a
+/// button with the mouse moves the focus to it as well.
+#[allow(clippy::type_complexity)]
+fn navigate_menus(
+    mut selection: ResMut<MenuSelection>,
+    mut buttons: Query<(Entity, &GlobalTransform, &mut Interaction), With<Button>>,
+    added: Query<(), Added<Button>>,
+    kb_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    pad_input: Res<ButtonInput<GamepadButton>>,
+) {
+    if let Some(entity) = selection.pressed.take() {
+        if let Ok((_, _, mut interaction)) = buttons.get_mut(entity) {
+            if *interaction == Interaction::Pressed {
+                *interaction = Interaction::None;
+            }
+        }
+    }
+
+    // A new screen starts with its first button focused
+    if !added.is_empty() {
+        selection.index = 0;
+    }
+
+    // Iterated mutably only to see which interactions changed, nothing is written
+    let mut order: Vec<(Entity, Vec2, bool)> = buttons
+        .iter_mut()
+        .map(|(entity, transform, interaction)| {
+            let hovered = interaction.is_changed() && *interaction == Interaction::Hovered;
+            (entity, transform.translation().truncate(), hovered)
+        })
+        .collect();
+
+    if order.is_empty() {
+        selection.focused = None;
+        return;
+    }
+
+    // UI coordinates grow downwards, so this is top to bottom
+    order.sort_by(|(_, a, _), (_, b, _)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+
+    if let Some(hovered) = order.iter().position(|&(_, _, hovered)| hovered) {
+        selection.index = hovered;
+    }
+
+    let pad_pressed = |button_type| {
+        gamepads
+            .iter()
+            .any(|gamepad| pad_input.just_pressed(GamepadButton::new(gamepad, button_type)))
+    };
+
+    let up = kb_input.any_just_pressed([KeyCode::ArrowUp, KeyCode::KeyW])
+        || pad_pressed(GamepadButtonType::DPadUp);
+    let down = kb_input.any_just_pressed([KeyCode::ArrowDown, KeyCode::KeyS])
+        || pad_pressed(GamepadButtonType::DPadDown);
+
+    let count = order.len();
+    // Keep the index on screen if the buttons changed under it, then wrap
+    // around at either end
+    let mut index = selection.index.min(count - 1);
+    if up {
+        index = (index + count - 1) % count;
+    }
+    if down {
+        index = (index + 1) % count;
+    }
+
+    let focused = order[index].0;
+    selection.index = index;
+    selection.focused = Some(focused);
+
+    if kb_input.just_pressed(KeyCode::Enter) || pad_pressed(GamepadButtonType::South) {
+        if let Ok((_, _, mut interaction)) = buttons.get_mut(focused) {
+            *interaction = Interaction::Pressed;
+            selection.pressed = Some(focused);
+        }
+    }
+}
+
+/// Highlight buttons while they are focused, hovered or pressed.
+fn button_colors(
+    mut buttons: Query<(Entity, &Interaction, &mut BackgroundColor), With<Button>>,
+    selection: Res<MenuSelection>,
+) {
+    for (entity, interaction, mut color) in &mut buttons {
+        let focused = selection.focused == Some(entity);
+
+        let new_color = match interaction {
+            Interaction::Pressed => BUTTON_PRESSED_COLOR,
+            Interaction::Hovered => BUTTON_HOVERED_COLOR,
+            Interaction::None if focused => BUTTON_FOCUSED_COLOR,
+            Interaction::None => BUTTON_COLOR,
+        };
+
+        // Only write on a change, so the color isn't flagged as changed every frame
+        if color.0 != new_color {
+            color.0 = new_color;
+        }
+    }
+}
+
+/// Spawn the player's health bar in the top left corner of the screen.
+fn setup_health_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            StateScoped(GameState::Playing),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(20.),
+                    top: Val::Px(20.),
+                    width: Val::Px(HEALTH_BAR_SIZE.x),
+                    height: Val::Px(HEALTH_BAR_SIZE.y),
+                    ..default()
+                },
+                background_color: Color::srgb(0.1, 0.1, 0.1).into(),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                HealthBar,
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.),
+                        height: Val::Percent(100.),
+                        ..default()
+                    },
+                    background_color: Color::srgb(0.0, 0.8, 0.0).into(),
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// Spawn the low health vignette, hidden until health drops.
+///
+/// The texture is generated instead of loaded: white, clear in the middle and
+/// fading in towards the edges, so tinting it gives the glow its color.
+fn setup_vignette(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let size = VIGNETTE_TEXTURE_SIZE;
+    let data = (0..size * size)
+        .flat_map(|i| {
+            let pixel = Vec2::new((i % size) as f32, (i / size) as f32) + 0.5;
+            // 0 in the center, 1 in the middle of each edge
+            let distance = (pixel / size as f32 * 2. - 1.).length();
+            let alpha = ((distance - 0.5) / 0.7).clamp(0., 1.);
+            [255, 255, 255, (alpha * alpha * 255.) as u8]
+        })
+        .collect();
+    let image = Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+
+    commands.spawn((
+        Vignette,
+        StateScoped(GameState::Playing),
+        ImageBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                ..default()
+            },
+            image: UiImage::new(images.add(image)).with_color(Color::NONE),
+            visibility: Visibility::Hidden,
+            // Behind the rest of the HUD
+            z_index: ZIndex::Global(-1),
+            ..default()
+        },
+    ));
+}
+
+/// Spawn the stamina bar just under the health bar.
+fn setup_stamina_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            StateScoped(GameState::Playing),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(20.),
+                    top: Val::Px(20. + HEALTH_BAR_SIZE.y + 4.),
+                    width: Val::Px(HEALTH_BAR_SIZE.x),
+                    height: Val::Px(STAMINA_BAR_HEIGHT),
+                    ..default()
+                },
+                background_color: Color::srgb(0.1, 0.1, 0.1).into(),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                StaminaBar,
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.),
+                        height: Val::Percent(100.),
+                        ..default()
+                    },
+                    background_color: Color::srgb(0.9, 0.8, 0.1).into(),
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// Spawn the attack cooldown bar just under the stamina bar.
+fn setup_cooldown_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            StateScoped(GameState::Playing),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(20.),
+                    top: Val::Px(20. + HEALTH_BAR_SIZE.y + 4. + STAMINA_BAR_HEIGHT + 4.),
+                    width: Val::Px(HEALTH_BAR_SIZE.x),
+                    height: Val::Px(COOLDOWN_BAR_HEIGHT),
+                    ..default()
+                },
+                background_color: Color::srgb(0.1, 0.1, 0.1).into(),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                CooldownBar,
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(0.),
+                        height: Val::Percent(100.),
+                        ..default()
+                    },
+                    background_color: Color::srgb(0.8, 0.8, 0.8).into(),
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// Spawn the ultimate charge bar just under the attack cooldown bar, with the
+/// label that shows when it is ready.
+fn setup_ultimate_ui(mut commands: Commands, bindings: Res<KeyBindings>) {
+    let top = 20. + HEALTH_BAR_SIZE.y + 4. + STAMINA_BAR_HEIGHT + 4. + COOLDOWN_BAR_HEIGHT + 4.;
+
+    commands
+        .spawn((
+            StateScoped(GameState::Playing),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(20.),
+                    top: Val::Px(top),
+                    width: Val::Px(HEALTH_BAR_SIZE.x),
+                    height: Val::Px(ULTIMATE_BAR_HEIGHT),
+                    ..default()
+                },
+                background_color: Color::srgb(0.1, 0.1, 0.1).into(),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                UltimateBar,
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(0.),
+                        height: Val::Percent(100.),
+                        ..default()
+                    },
+                    background_color: Color::srgb(0.5, 0.4, 0.2).into(),
+                    ..default()
+                },
+            ));
+        });
+
+    commands.spawn((
+        UltimateReadyText,
+        StateScoped(GameState::Playing),
+        TextBundle {
+            text: Text::from_section(
+                format!("Ultimate ready [{:?}]", bindings.ultimate),
+                TextStyle {
+                    font_size: 16.,
+                    color: Color::srgb(1.0, 0.8, 0.3),
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(20. + HEALTH_BAR_SIZE.x + 8.),
+                top: Val::Px(top - 5.),
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+    ));
+}
+
+/// Spawn the combat log in the bottom left corner, starting a fresh log.
+fn setup_combat_log(mut commands: Commands, mut log: ResMut<CombatLog>) {
+    log.lines.clear();
+
+    commands.spawn((
+        CombatLogText,
+        StateScoped(GameState::Playing),
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 18.,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(20.),
+            bottom: Val::Px(20.),
+            ..default()
+        }),
+    ));
+}
+
+/// Append new combat events and level ups to the log, dropping the oldest
+/// past the cap.
+fn combat_log_ui(
+    mut events: EventReader<CombatEvent>,
+    mut level_ups: EventReader<LevelUp>,
+    mut boss_phases: EventReader<BossPhaseChanged>,
+    mut log: ResMut<CombatLog>,
+    mut text: Query<&mut Text, With<CombatLogText>>,
+) {
+    if events.is_empty() && level_ups.is_empty() && boss_phases.is_empty() {
+        return;
+    }
+
+    let combat_lines = events.read().map(|event| match event {
+        CombatEvent::EnemyKilled => "Opponent defeated".to_string(),
+        CombatEvent::PlayerHit(amount) => format!("You took {amount:.0} damage"),
+        CombatEvent::EnemyHit(amount) => format!("Hit an opponent for {amount:.0}"),
+    });
+    let level_lines = level_ups
+        .read()
+        .map(|level_up| format!("Level up! Now level {}", level_up.level));
+    let boss_lines = boss_phases.read().map(|BossPhaseChanged(phase)| {
+        match phase {
+            BossPhase::Chase => "The boss closes in",
+            BossPhase::Charge => "The boss readies a charge!",
+            BossPhase::Summon => "The boss calls for help!",
+        }
+        .to_string()
+    });
+
+    for line in combat_lines.chain(level_lines).chain(boss_lines) {
+        log.lines.push_back(line);
+
+        if log.lines.len() > COMBAT_LOG_LINES {
+            log.lines.pop_front();
+        }
+    }
+
+    for mut text in &mut text {
+        text.sections[0].value = log.lines.iter().cloned().collect::<Vec<_>>().join("\n");
+    }
+}
+
+/// Spawn the empty minimap panel in the bottom right corner of the screen.
+fn setup_minimap(mut commands: Commands) {
+    commands.spawn((
+        Minimap,
+        StateScoped(GameState::Playing),
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                right: Val::Px(20.),
+                bottom: Val::Px(20.),
+                width: Val::Px(MINIMAP_SIZE.x),
+                height: Val::Px(MINIMAP_SIZE.y),
+                ..default()
+            },
+            background_color: Color::srgba(0.1, 0.1, 0.1, 0.7).into(),
+            ..default()
+        },
+    ));
+}
+
+/// Point arrows from the edge of the window towards the nearest opponents out
+/// of view, fainter the further they are from the player.
+///
+/// Each opponent is projected to the window, and the ones landing outside it
+/// get an arrow where the line from the window center towards them meets the
+/// edge. The arrows are drawn into the world, sized by the zoom so they keep
+/// the same size on screen.
+fn offscreen_indicators(
+    mut gizmos: Gizmos,
+    camera: Query<(&Camera, &Transform, &OrthographicProjection), With<WorldCamera>>,
+    player: Query<&Transform, With<Player>>,
+    opponents: Query<&Transform, (With<Opponent>, Without<Dying>)>,
+) {
+    let Ok((camera, camera_transform, projection)) = camera.get_single() else {
+        return;
+    };
+
+    let Ok(player_transform) = player.get_single() else {
+        return;
+    };
+
+    let Some(viewport) = camera.logical_viewport_size() else {
+        return;
+    };
+
+    // The camera systems already moved it this frame, after transforms were
+    // last propagated, and it has no parent
+    let camera_global = GlobalTransform::from(*camera_transform);
+    let player_position = player_transform.translation.truncate();
+    let center = viewport / 2.;
+    let inner = (center - Vec2::splat(INDICATOR_MARGIN)).max(Vec2::ONE);
+
+    let mut offscreen: Vec<(f32, Vec2)> = opponents
+        .iter()
+        .filter_map(|transform| {
+            let position = transform.translation.truncate();
+            let screen = camera.world_to_viewport(&camera_global, position.extend(0.))?;
+            let visible = screen.cmpge(Vec2::ZERO).all() && screen.cmple(viewport).all();
+            (!visible).then(|| (player_position.distance(position), screen))
+        })
+        .collect();
+    offscreen.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    for (distance, screen) in offscreen.into_iter().take(MAX_OFFSCREEN_INDICATORS) {
+        let offset = screen - center;
+        let Some(direction) = offset.try_normalize() else {
+            continue;
+        };
+
+        // Shrunk until the first axis touches the margin
+        let tip = center + offset * (inner / offset.abs()).min_element();
+        let tail = tip - direction * INDICATOR_LENGTH;
+
+        let (Some(tip), Some(tail)) = (
+            camera.viewport_to_world_2d(&camera_global, tip),
+            camera.viewport_to_world_2d(&camera_global, tail),
+        ) else {
+            continue;
+        };
+
+        let alpha = (1. - distance / INDICATOR_FADE_DISTANCE).max(INDICATOR_MIN_ALPHA);
+        gizmos
+            .arrow_2d(tail, tip, Color::srgb(1.0, 0.3, 0.3).with_alpha(alpha))
+            .with_tip_length(INDICATOR_LENGTH / 2. * projection.scale);
+    }
+}
+
+/// Keep one dot on the minimap per player and opponent, placed where they are
+/// in the arena.
+#[allow(clippy::type_complexity)]
+fn update_minimap(
+    mut commands: Commands,
+    minimap: Query<Entity, With<Minimap>>,
+    targets: Query<
+        (Entity, &Transform, Has<Player>),
+        (Or<(With<Player>, With<Opponent>)>, Without<Dying>),
+    >,
+    mut dots: Query<(Entity, &MinimapDot, &mut Style)>,
+    bounds: Res<WorldBounds>,
+) {
+    let Ok(minimap) = minimap.get_single() else {
+        return;
+    };
+
+    // Map world coordinates onto the panel, keeping the whole dot inside it
+    let to_minimap = |position: Vec2| {
+        let fraction = (position + bounds.half_extents()) / bounds.size;
+        // UI coordinates grow downwards while world ones grow upwards
+        let pixels = Vec2::new(fraction.x, 1. - fraction.y) * MINIMAP_SIZE;
+        (pixels - MINIMAP_DOT_SIZE / 2.).clamp(Vec2::ZERO, MINIMAP_SIZE - MINIMAP_DOT_SIZE)
+    };
+
+    let mut tracked = HashSet::new();
+
+    for (dot_entity, dot, mut style) in &mut dots {
+        let Ok((_, transform, _)) = targets.get(dot.target) else {
+            // Whatever it followed is gone or fading out
+            commands.entity(dot_entity).despawn_recursive();
+            continue;
+        };
+
+        let position = to_minimap(transform.translation.truncate());
+        style.left = Val::Px(position.x);
+        style.top = Val::Px(position.y);
+        tracked.insert(dot.target);
+    }
+
+    for (target, transform, is_player) in &targets {
+        if tracked.contains(&target) {
+            continue;
+        }
+
+        let position = to_minimap(transform.translation.truncate());
+        let color = if is_player {
+            Color::srgb(0.0, 1.0, 0.0)
+        } else {
+            Color::srgb(1.0, 0.0, 0.0)
+        };
+
+        let dot = commands
+            .spawn((
+                MinimapDot { target },
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left: Val::Px(position.x),
+                        top: Val::Px(position.y),
+                        width: Val::Px(MINIMAP_DOT_SIZE),
+                        height: Val::Px(MINIMAP_DOT_SIZE),
+                        ..default()
+                    },
+                    background_color: color.into(),
+                    border_radius: BorderRadius::MAX,
+                    // Keep the player on top of the opponents crowding it
+                    z_index: ZIndex::Local(is_player as i32),
+                    ..default()
+                },
+            ))
+            .id();
+
+        commands.entity(minimap).add_child(dot);
+    }
+}
+
+/// Spawn the score counter in the top right corner of the screen.
+fn setup_score_ui(mut commands: Commands) {
+    commands.spawn((
+        ScoreText,
+        StateScoped(GameState::Playing),
+        TextBundle::from_section(
+            "Score: 0",
+            TextStyle {
+                font_size: 30.,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(20.),
+            top: Val::Px(16.),
+            ..default()
+        }),
+    ));
+
+    commands.spawn((
+        LevelText,
+        StateScoped(GameState::Playing),
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(20.),
+            top: Val::Px(52.),
+            ..default()
+        }),
+    ));
+
+    commands.spawn((
+        ComboText,
+        StateScoped(GameState::Playing),
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.,
+                color: Color::srgb(1.0, 0.6, 0.2),
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(20.),
+            top: Val::Px(80.),
+            ..default()
+        }),
+    ));
+
+    commands.spawn((
+        DifficultyText,
+        StateScoped(GameState::Playing),
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.,
+                color: Color::srgb(0.9, 0.4, 0.4),
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(20.),
+            top: Val::Px(112.),
+            ..default()
+        }),
+    ));
+}
+
+/// Resize and recolor the health bar to match the player's health.
+fn update_health_ui(
+    player: Query<&Health, With<Player>>,
+    mut bar: Query<(&mut Style, &mut BackgroundColor), With<HealthBar>>,
+) {
+    let Ok(health) = player.get_single() else {
+        return;
+    };
+
+    let Ok((mut style, mut color)) = bar.get_single_mut() else {
+        return;
+    };
+
+    let fraction = health.fraction();
+    style.width = Val::Percent(fraction * 100.);
+
+    *color = if fraction < LOW_HEALTH_FRACTION {
+        Color::srgb(0.9, 0.1, 0.1).into()
+    } else {
+        Color::srgb(0.0, 0.8, 0.0).into()
+    };
+}
+
+/// Pulse the vignette, stronger the lower the player's health, and hide it
+/// completely at safe health.
+fn low_health_vignette(
+    player: Query<&Health, With<Player>>,
+    mut vignette: Query<(&mut UiImage, &mut Visibility), With<Vignette>>,
+    time: Res<Time>,
+) {
+    let Ok(health) = player.get_single() else {
+        return;
+    };
+
+    let Ok((mut image, mut visibility)) = vignette.get_single_mut() else {
+        return;
+    };
+
+    let danger = 1. - health.fraction() / VIGNETTE_HEALTH_FRACTION;
+
+    if danger <= 0. {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+
+    let pulse = 0.75 + 0.25 * (time.elapsed_seconds() * VIGNETTE_PULSE_SPEED).sin();
+    let alpha = danger.min(1.) * pulse * VIGNETTE_MAX_ALPHA;
+    image.color = Color::srgba(0.8, 0.0, 0.0, alpha);
+    *visibility = Visibility::Inherited;
+}
+
+fn update_stamina_ui(
+    player: Query<&Stamina, With<Player>>,
+    mut bar: Query<&mut Style, With<StaminaBar>>,
+) {
+    let Ok(stamina) = player.get_single() else {
+        return;
+    };
+
+    let Ok(mut style) = bar.get_single_mut() else {
+        return;
+    };
+
+    style.width = Val::Percent(stamina.fraction() * 100.);
+}
+
+/// Shrink the cooldown overlay as the player's next attack gets closer.
+fn update_cooldown_ui(
+    player: Query<&AttackCooldown, With<Player>>,
+    mut bar: Query<&mut Style, With<CooldownBar>>,
+) {
+    let Ok(cooldown) = player.get_single() else {
+        return;
+    };
+
+    let Ok(mut style) = bar.get_single_mut() else {
+        return;
+    };
+
+    style.width = Val::Percent(cooldown.remaining_fraction() * 100.);
+}
+
+/// Fill the ultimate bar as it charges, lighting it up and showing the ready
+/// label once it can be used.
+fn update_ultimate_ui(
+    player: Query<&UltimateCharge, With<Player>>,
+    mut bar: Query<(&mut Style, &mut BackgroundColor), With<UltimateBar>>,
+    mut label: Query<&mut Visibility, With<UltimateReadyText>>,
+) {
+    let Ok(charge) = player.get_single() else {
+        return;
+    };
+
+    let Ok((mut style, mut color)) = bar.get_single_mut() else {
+        return;
+    };
+
+    style.width = Val::Percent(charge.fraction() * 100.);
+
+    *color = if charge.is_ready() {
+        Color::srgb(1.0, 0.8, 0.3).into()
+    } else {
+        Color::srgb(0.5, 0.4, 0.2).into()
+    };
+
+    for mut visibility in &mut label {
+        *visibility = if charge.is_ready() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn update_score_text(score: Res<Score>, mut text: Query<&mut Text, With<ScoreText>>) {
+    for mut text in &mut text {
+        text.sections[0].value = format!("Score: {}", score.0);
+    }
+}
+
+fn update_level_text(
+    player: Query<&Experience, (With<Player>, Changed<Experience>)>,
+    mut text: Query<&mut Text, With<LevelText>>,
+) {
+    let Ok(experience) = player.get_single() else {
+        return;
+    };
+
+    for mut text in &mut text {
+        text.sections[0].value = format!(
+            "Level {}  XP {:.0}/{:.0}",
+            experience.level, experience.current, experience.to_next
+        );
+    }
+}
+
+/// Show the difficulty tier counting from one, the way the player sees it.
+fn update_difficulty_text(
+    difficulty: Res<Difficulty>,
+    mut text: Query<&mut Text, With<DifficultyText>>,
+) {
+    for mut text in &mut text {
+        text.sections[0].value = format!("Difficulty {}", difficulty.tier + 1);
+    }
+}
+
+/// Show the combo once it is more than a single hit.
+fn update_combo_text(combo: Res<Combo>, mut text: Query<&mut Text, With<ComboText>>) {
+    let value = if combo.count >= 2 {
+        format!("Combo x{}  ({:.1}x damage)", combo.count, combo.damage_multiplier())
+    } else {
+        String::new()
+    };
+
+    for mut text in &mut text {
+        // The timer ticks every frame, so only touch the text when it differs
+        if text.sections[0].value != value {
+            text.sections[0].value.clone_from(&value);
+        }
+    }
+}
+
+/// Attach a health bar to every newly spawned opponent.
+///
+/// The bar is a child of the opponent, so it lives in world space and follows
+/// it around without any extra bookkeeping.
+fn spawn_enemy_healthbars(
+    mut commands: Commands,
+    opponents: Query<(Entity, &Collidable), Added<Opponent>>,
+) {
+    for (opponent, collidable) in &opponents {
+        let fill = commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::srgb(0.9, 0.1, 0.1),
+                    custom_size: Some(ENEMY_HEALTH_BAR_SIZE),
+                    // Grow from the left edge when scaled
+                    anchor: Anchor::CenterLeft,
+                    ..default()
+                },
+                transform: Transform::from_xyz(-ENEMY_HEALTH_BAR_SIZE.x / 2., 0., 0.1),
+                ..default()
+            })
+            .id();
+
+        let bar = commands
+            .spawn((
+                EnemyHealthBar { fill },
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::srgb(0.1, 0.1, 0.1),
+                        custom_size: Some(ENEMY_HEALTH_BAR_SIZE),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(
+                        0.,
+                        collidable.radius + ENEMY_HEALTH_BAR_GAP,
+                        1.,
+                    ),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+            ))
+            .add_child(fill)
+            .id();
+
+        commands.entity(opponent).add_child(bar);
+    }
+}
+
+/// Scale opponent health bars by their health, hiding them at full health.
+fn update_enemy_healthbars(
+    opponents: Query<(&Health, &Children), With<Opponent>>,
+    mut bars: Query<(&EnemyHealthBar, &mut Visibility)>,
+    mut fills: Query<&mut Transform>,
+) {
+    for (health, children) in &opponents {
+        for &child in children {
+            let Ok((bar, mut visibility)) = bars.get_mut(child) else {
+                continue;
+            };
+
+            let fraction = health.fraction();
+
+            *visibility = if fraction >= 1. {
+                Visibility::Hidden
+            } else {
+                Visibility::Inherited
+            };
+
+            if let Ok(mut transform) = fills.get_mut(bar.fill) {
+                transform.scale.x = fraction;
+            }
+        }
+    }
+}
+
+fn spawn_pause_overlay(mut commands: Commands) {
+    commands
+        .spawn((
+            PauseOverlay,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.),
+                    ..default()
+                },
+                background_color: Color::srgba(0., 0., 0., 0.6).into(),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Paused",
+                TextStyle {
+                    font_size: 60.,
+                    ..default()
+                },
+            ));
+
+            spawn_button(parent, ResumeButton, "Resume", 30.);
+
+            spawn_button(parent, SaveButton, "Save", 30.);
+        });
+}
+
+fn despawn_pause_overlay(mut commands: Commands, overlay: Query<Entity, With<PauseOverlay>>) {
+    for entity in &overlay {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Resume the game when the resume button is clicked.
+fn resume_button(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<ResumeButton>)>,
+    mut next_state: ResMut<NextState<PauseState>>,
+) {
+    for interaction in &buttons {
+        if *interaction == Interaction::Pressed {
+            next_state.set(PauseState::Running);
+        }
+    }
+}
+
+/// Save the run when the save button is clicked.
+fn save_button(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<SaveButton>)>,
+    mut requests: EventWriter<SaveRequested>,
+) {
+    for interaction in &buttons {
+        if *interaction == Interaction::Pressed {
+            requests.send(SaveRequested);
+        }
+    }
+}
+
+fn spawn_game_over_screen(mut commands: Commands) {
+    commands
+        .spawn((
+            StateScoped(GameState::GameOver),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.),
+                    ..default()
+                },
+                background_color: Color::srgba(0.2, 0., 0., 0.8).into(),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "You Died",
+                TextStyle {
+                    font_size: 60.,
+                    ..default()
+                },
+            ));
+
+            spawn_button(parent, RetryButton, "Retry", 30.);
+        });
+}
+
+/// Spawn the victory screen with the final score.
+fn spawn_victory_screen(mut commands: Commands, score: Res<Score>) {
+    commands
+        .spawn((
+            StateScoped(GameState::Victory),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.),
+                    ..default()
+                },
+                background_color: Color::srgba(0., 0.15, 0., 0.8).into(),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Victory",
+                TextStyle {
+                    font_size: 60.,
+                    ..default()
+                },
+            ));
+
+            parent.spawn(TextBundle::from_section(
+                format!("Final score: {}", score.0),
+                TextStyle {
+                    font_size: 30.,
+                    ..default()
+                },
+            ));
+
+            spawn_button(parent, RetryButton, "Play Again", 30.);
+        });
+}
+
+/// Start a new run when the retry button is clicked.
+fn retry_button(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<RetryButton>)>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for interaction in &buttons {
+        if *interaction == Interaction::Pressed {
+            next_state.set(GameState::Playing);
+        }
+    }
+}
@@ -0,0 +1,373 @@
+use std::fs;
+
+use bevy::asset::{AssetLoadFailedEvent, LoadState};
+use bevy::math::vec2;
+use bevy::prelude::*;
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+use serde::{Deserialize, Serialize};
+
+use crate::components::{Collidable, CollisionLayers, SpriteFallback, StaticBody};
+use crate::state::GameState;
+
+/// Where the obstacle layout is kept, relative to the working directory.
+/// Written by the level editor.
+pub const LAYOUT_PATH: &str = "layout.ron";
+
+/// Color of the arena floor.
+pub const ARENA_COLOR: Color = Color::srgb(0.2, 0.2, 0.3);
+
+/// Color of every obstacle.
+pub const OBSTACLE_COLOR: Color = Color::srgb(0.4, 0.4, 0.45);
+
+/// Size of the arena a run starts with.
+const DEFAULT_ARENA_SIZE: Vec2 = Vec2::new(2000., 1400.);
+
+/// Smallest arena `ArenaSize::set` allows, so there is always room to move
+/// around.
+const MIN_ARENA_SIZE: Vec2 = Vec2::new(400., 300.);
+
+/// Requested size of the arena, the one place to change it from.
+///
+/// Changes are picked up at the end of the frame by `apply_arena_size`, which
+/// updates `WorldBounds` and everything built from the old size.
+#[derive(Resource)]
+pub struct ArenaSize(pub Vec2);
+
+impl Default for ArenaSize {
+    fn default() -> Self {
+        Self(DEFAULT_ARENA_SIZE)
+    }
+}
+
+impl ArenaSize {
+    /// Resize the arena, no smaller than `MIN_ARENA_SIZE`. The floor is rebuilt
+    /// and bodies left outside are pulled back in on the same frame.
+    pub fn set(&mut self, size: Vec2) {
+        self.0 = size.max(MIN_ARENA_SIZE);
+    }
+}
+
+/// Size of the playable arena, centered on the origin.
+///
+/// Follows `ArenaSize` and is never changed directly. Everything that clamps
+/// to it reads it fresh each time.
+#[derive(Resource)]
+pub struct WorldBounds {
+    pub size: Vec2,
+}
+
+impl Default for WorldBounds {
+    fn default() -> Self {
+        Self {
+            size: DEFAULT_ARENA_SIZE,
+        }
+    }
+}
+
+impl WorldBounds {
+
+    pub fn half_extents(&self) -> Vec2 {
+        self.size / 2.
+    }
+
+    /// Closest position to `position` where a circle of `radius` fits fully
+    /// inside the arena. Everything that spawns or moves bodies should go
+    /// through this so nothing ends up poking out past the edge.
+    pub fn clamp_world_position(&self, position: Vec2, radius: f32) -> Vec2 {
+        let limit = (self.half_extents() - Vec2::splat(radius)).max(Vec2::ZERO);
+        position.clamp(-limit, limit)
+    }
+}
+
+#[derive(Component)]
+pub struct Obstacle;
+
+/// The floor of the arena, sized to `WorldBounds`.
+#[derive(Component)]
+pub struct ArenaFloor;
+
+/// A single obstacle in an `ObstacleLayout`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct ObstacleSpec {
+    pub position: [f32; 2],
+    pub radius: f32,
+}
+
+/// Obstacles placed in the arena at the start of every run.
+///
+/// Read from `LAYOUT_PATH` when the game starts, falling back to the built in
+/// layout, and replaced whenever the level editor saves.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+pub struct ObstacleLayout {
+    pub obstacles: Vec<ObstacleSpec>,
+}
+
+impl Default for ObstacleLayout {
+    fn default() -> Self {
+        let obstacles = [
+            (vec2(-400., -100.), 60.),
+            (vec2(350., 250.), 40.),
+            (vec2(0., 400.), 80.),
+            (vec2(500., -300.), 50.),
+        ]
+        .map(|(position, radius)| ObstacleSpec {
+            position: position.to_array(),
+            radius,
+        })
+        .to_vec();
+
+        Self { obstacles }
+    }
+}
+
+impl ObstacleLayout {
+    /// Read a layout from `path`, falling back to the default one if the file
+    /// is missing or can't be parsed.
+    pub fn load(path: &str) -> Self {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                info!("No obstacle layout loaded from {path} ({err}), using the default");
+                return Self::default();
+            }
+        };
+
+        match ron::from_str::<Self>(&text) {
+            Ok(layout) => layout,
+            Err(err) => {
+                warn!("Invalid obstacle layout in {path}, using the default: {err}");
+                Self::default()
+            }
+        }
+    }
+
+    /// Write the layout to `path`. Failing to save is only worth a warning.
+    pub fn save(&self, path: &str) {
+        let text = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(text) => text,
+            Err(err) => {
+                warn!("Couldn't serialize the obstacle layout: {err}");
+                return;
+            }
+        };
+
+        match fs::write(path, text) {
+            Ok(()) => info!("Saved the obstacle layout to {path}"),
+            Err(err) => warn!("Couldn't save the obstacle layout to {path}: {err}"),
+        }
+    }
+}
+
+/// Whether the straight line from `from` to `to` stays clear of every
+/// obstacle circle in `obstacles`.
+///
+/// A line that only grazes the edge of a circle still counts as clear, so
+/// something just peeking past an obstacle can be seen.
+pub fn line_of_sight<'a>(
+    from: Vec2,
+    to: Vec2,
+    obstacles: impl IntoIterator<Item = (Vec2, &'a Collidable)>,
+) -> bool {
+    let segment = to - from;
+    let length_squared = segment.length_squared();
+
+    obstacles.into_iter().all(|(center, collidable)| {
+        // Closest point to the center along the segment, clamped to its ends
+        let t = if length_squared == 0. {
+            0.
+        } else {
+            ((center - from).dot(segment) / length_squared).clamp(0., 1.)
+        };
+        let closest = from + segment * t;
+        closest.distance_squared(center) >= collidable.radius * collidable.radius
+    })
+}
+
+pub struct WorldPlugin;
+
+impl Plugin for WorldPlugin {
+    fn build(&self, app: &mut App) {
+        // Loaded right away like `Settings`, so both the first run and the
+        // level editor start from the saved layout
+        let layout = ObstacleLayout::load(LAYOUT_PATH);
+
+        app.init_resource::<ArenaSize>()
+            .init_resource::<WorldBounds>()
+            .insert_resource(layout)
+            .add_systems(OnEnter(GameState::Playing), spawn_arena)
+            // So a run that resized the arena doesn't leave the next run or
+            // the level editor with its size
+            .add_systems(OnExit(GameState::Playing), reset_arena_size)
+            // Last, so a resize from any gameplay system applies this frame
+            .add_systems(
+                PostUpdate,
+                apply_arena_size.run_if(resource_changed::<ArenaSize>),
+            )
+            .add_systems(Update, apply_sprite_fallback);
+    }
+}
+
+fn reset_arena_size(mut arena: ResMut<ArenaSize>) {
+    *arena = ArenaSize::default();
+}
+
+fn spawn_arena(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    bounds: Res<WorldBounds>,
+    layout: Res<ObstacleLayout>,
+) {
+    spawn_arena_floor(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        &bounds,
+        GameState::Playing,
+    );
+
+    let obstacle_material = materials.add(OBSTACLE_COLOR);
+
+    for obstacle in &layout.obstacles {
+        spawn_obstacle(
+            &mut commands,
+            &mut meshes,
+            &obstacle_material,
+            GameState::Playing,
+            Vec2::from_array(obstacle.position),
+            obstacle.radius,
+        );
+    }
+}
+
+/// Spawn the floor the arena is drawn on, scoped to `state`.
+pub fn spawn_arena_floor(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    bounds: &WorldBounds,
+    state: GameState,
+) -> Entity {
+    commands
+        .spawn((
+            ArenaFloor,
+            StateScoped(state),
+            MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(meshes.add(Rectangle::from_size(bounds.size))),
+                material: materials.add(ARENA_COLOR),
+                ..default()
+            },
+        ))
+        .id()
+}
+
+/// Bring `WorldBounds` and everything built from the arena size up to date
+/// after `ArenaSize` changes.
+///
+/// The floor gets a mesh of the new size, and moving bodies caught outside a
+/// shrunk arena are pulled back in. Obstacles are left where they are, the
+/// layout decides those. The camera and movement clamps read the size every
+/// frame and need nothing here.
+#[allow(clippy::type_complexity)]
+fn apply_arena_size(
+    arena: Res<ArenaSize>,
+    mut bounds: ResMut<WorldBounds>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut floors: Query<&mut Mesh2dHandle, With<ArenaFloor>>,
+    mut bodies: Query<(&mut Transform, &Collidable), (Without<StaticBody>, Without<ArenaFloor>)>,
+) {
+    bounds.size = arena.0;
+
+    for mut mesh in &mut floors {
+        *mesh = Mesh2dHandle(meshes.add(Rectangle::from_size(bounds.size)));
+    }
+
+    for (mut transform, collidable) in &mut bodies {
+        let position = transform.translation.truncate();
+        let clamped = bounds.clamp_world_position(position, collidable.radius);
+        transform.translation = clamped.extend(transform.translation.z);
+    }
+}
+
+/// Spawn a single solid obstacle, scoped to `state`.
+pub fn spawn_obstacle(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    material: &Handle<ColorMaterial>,
+    state: GameState,
+    position: Vec2,
+    radius: f32,
+) -> Entity {
+    commands
+        .spawn((
+            Obstacle,
+            StateScoped(state),
+            StaticBody,
+            Collidable { radius },
+            CollisionLayers::OBSTACLE,
+            MaterialMesh2dBundle {
+                mesh: meshes.add(Circle::new(radius)).into(),
+                material: material.clone(),
+                transform: Transform {
+                    translation: position.extend(0.5),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .id()
+}
+
+/// Swap sprites whose texture failed to load for a plain colored square.
+///
+/// Sprites spawned after the failure, like later waves reusing the same
+/// handle, never see the event, so new ones check the load state instead.
+fn apply_sprite_fallback(
+    mut commands: Commands,
+    mut failed: EventReader<AssetLoadFailedEvent<Image>>,
+    asset_server: Res<AssetServer>,
+    mut sprites: Query<(Entity, &mut Handle<Image>, &mut Sprite, Ref<SpriteFallback>)>,
+) {
+    let failed: Vec<AssetId<Image>> = failed
+        .read()
+        .map(|event| {
+            warn!("Using fallback color for missing sprite {}", event.path);
+            event.id
+        })
+        .collect();
+
+    for (entity, mut texture, mut sprite, fallback) in &mut sprites {
+        let missing = failed.contains(&texture.id())
+            || (fallback.is_added()
+                && matches!(asset_server.load_state(texture.id()), LoadState::Failed(_)));
+
+        if missing {
+            // The default image handle is a white pixel, so it takes the tint as is
+            *texture = Handle::default();
+            sprite.color = fallback.0;
+            // A single pixel has no frames to cut out of it
+            commands.entity(entity).remove::<TextureAtlas>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OBSTACLE: Collidable = Collidable { radius: 10. };
+
+    #[test]
+    fn grazing_an_obstacle_keeps_line_of_sight() {
+        let obstacles = [(Vec2::new(50., 10.), &OBSTACLE)];
+
+        assert!(line_of_sight(Vec2::ZERO, Vec2::new(100., 0.), obstacles));
+    }
+
+    #[test]
+    fn obstacle_across_the_line_blocks_sight() {
+        let obstacles = [(Vec2::new(50., 0.), &OBSTACLE)];
+
+        assert!(!line_of_sight(Vec2::ZERO, Vec2::new(100., 0.), obstacles));
+    }
+}